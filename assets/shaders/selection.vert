@@ -0,0 +1,26 @@
+#version 450
+
+layout(location = 0) out vec4 outColor;
+layout(push_constant) uniform pushConstants {
+    mat4 mvpTransform;
+    vec4 color;
+};
+
+vec3 edgeVertexCoords[24] = vec3[](
+    vec3(0, 0, 0), vec3(1, 0, 0),  // x edges
+    vec3(0, 1, 0), vec3(1, 1, 0),
+    vec3(0, 0, 1), vec3(1, 0, 1),
+    vec3(0, 1, 1), vec3(1, 1, 1),
+    vec3(0, 0, 0), vec3(0, 1, 0),  // y edges
+    vec3(1, 0, 0), vec3(1, 1, 0),
+    vec3(0, 0, 1), vec3(0, 1, 1),
+    vec3(1, 0, 1), vec3(1, 1, 1),
+    vec3(0, 0, 0), vec3(0, 0, 1),  // z edges
+    vec3(1, 0, 0), vec3(1, 0, 1),
+    vec3(0, 1, 0), vec3(0, 1, 1),
+    vec3(1, 1, 0), vec3(1, 1, 1));
+
+void main() {
+    gl_Position = mvpTransform * vec4(edgeVertexCoords[gl_VertexIndex], 1);
+    outColor = color;
+}
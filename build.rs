@@ -1,39 +1,42 @@
-use std::io::Write;
+#[path = "src/shader_compiler.rs"]
+mod shader_compiler;
 
-const IN_DIRNAME: &str = "assets/shaders";
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("cargo:rerun-if-changed={}", IN_DIRNAME);
-    let out_dirname = std::env::var("OUT_DIR")?;
-
-    std::fs::create_dir_all(&out_dirname)?;
+fn main() {
+    println!("cargo:rerun-if-changed={}", shader_compiler::SHADER_DIR);
+    let out_dirname = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    std::fs::create_dir_all(&out_dirname).expect("Failed to create OUT_DIR");
 
     let mut compiler = shaderc::Compiler::new().expect("Failed to create shaderc compiler");
-
-    for entry in std::fs::read_dir(IN_DIRNAME)? {
-        let entry = entry?;
-        let in_path = entry.path();
-        let in_filename = in_path.file_name().unwrap().to_string_lossy();
-        let kind = match in_path.extension() {
-            Some(ext) => match ext.to_string_lossy().as_ref() {
-                "vert" => shaderc::ShaderKind::Vertex,
-                "frag" => shaderc::ShaderKind::Fragment,
-                _ => continue,
-            },
-            None => continue,
+    let in_paths = shader_compiler::shader_paths().unwrap_or_else(|err| {
+        eprintln!("failed to read {}: {}", shader_compiler::SHADER_DIR, err);
+        std::process::exit(1)
+    });
+
+    // Every shader is compiled before failing, so all of the errors show up at once.
+    let mut failed = 0;
+    for in_path in in_paths {
+        let shaders = match shader_compiler::compile(&mut compiler, &in_path) {
+            Ok(shaders) => shaders,
+            Err(err) => {
+                eprintln!("{}", err);
+                failed += 1;
+                continue;
+            }
         };
-        let out_path = format!("{}/{}.spv", out_dirname, in_filename);
-        println!(
-            "Compiling {} into {}",
-            in_path.to_str().expect("Invalid path"),
-            &out_path
-        );
-
-        let source = std::fs::read_to_string(&in_path)?;
-
-        let spirv = compiler.compile_into_spirv(&source, kind, &in_filename, "main", None)?;
-
-        std::fs::File::create(&out_path)?.write(spirv.as_binary_u8())?;
+        for shader in shaders {
+            let out_path = format!("{}/{}.spv", out_dirname, shader.name);
+            println!("Compiling {} into {}", in_path.display(), out_path);
+            for warning in shader.warnings.lines() {
+                println!("cargo:warning={}", warning)
+            }
+            if let Err(err) = std::fs::write(&out_path, &shader.spirv) {
+                eprintln!("failed to write {}: {}", out_path, err);
+                failed += 1
+            }
+        }
+    }
+    if failed > 0 {
+        eprintln!("{} shader(s) failed to compile", failed);
+        std::process::exit(1)
     }
-    Ok(())
 }
@@ -10,10 +10,66 @@ impl Camera {
         Self { pos, yaw, pitch }
     }
 
+    /// Camera `distance` away from `target`, looking at it from the given direction.
+    pub fn orbiting(target: uv::Vec3, distance: f32, yaw: f32, pitch: f32) -> Self {
+        let mut camera = Self::new(target, yaw, pitch);
+        camera.pos -= camera.look_dir() * distance;
+        camera
+    }
+
+    /// Camera at `pos` turned to face `target`.
+    pub fn looking_at(pos: uv::Vec3, target: uv::Vec3) -> Self {
+        let dir = (target - pos).normalized();
+        Self::new(
+            pos,
+            dir.y.atan2(dir.x).rem_euclid(std::f32::consts::TAU),
+            (-dir.z).clamp(-1.0, 1.0).acos(),
+        )
+    }
+
+    /// Blend between two cameras, turning the short way around.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let yaw_diff = (other.yaw - self.yaw + std::f32::consts::PI)
+            .rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+        Self {
+            pos: self.pos + (other.pos - self.pos) * t,
+            yaw: (self.yaw + yaw_diff * t).rem_euclid(std::f32::consts::TAU),
+            pitch: self.pitch + (other.pitch - self.pitch) * t,
+        }
+    }
+
+    pub fn yaw(&self) -> f32 {
+        self.yaw
+    }
+
+    pub fn pitch(&self) -> f32 {
+        self.pitch
+    }
+
     pub fn move_mat(&self) -> uv::Mat3 {
         uv::Mat3::from_rotation_z(self.yaw)
     }
 
+    /// Unit vector the camera is looking along.
+    pub fn look_dir(&self) -> uv::Vec3 {
+        uv::Vec3::new(
+            self.pitch.sin() * self.yaw.cos(),
+            self.pitch.sin() * self.yaw.sin(),
+            -self.pitch.cos(),
+        )
+    }
+
+    /// Unit vector to the right of the view, always horizontal.
+    pub fn right_dir(&self) -> uv::Vec3 {
+        uv::Vec3::new(self.yaw.sin(), -self.yaw.cos(), 0.0)
+    }
+
+    /// Unit vector up the view, perpendicular to `look_dir` and `right_dir`.
+    pub fn up_dir(&self) -> uv::Vec3 {
+        self.right_dir().cross(self.look_dir())
+    }
+
     pub fn look_mat(&self) -> uv::Mat4 {
         uv::Mat4::from_rotation_x(-self.pitch)
             * uv::Mat4::from_rotation_z(std::f32::consts::FRAC_PI_2 - self.yaw)
@@ -0,0 +1,146 @@
+use crate::camera::Camera;
+
+/// Camera pose at a point in time, in seconds from the start of the path.
+#[derive(Debug, Copy, Clone)]
+pub struct Keyframe {
+    pub time: f32,
+    pub camera: Camera,
+}
+
+/// Keyframes in time order, played back along a Catmull-Rom spline through them.
+///
+/// Saved as plain text, one keyframe per line: `time x y z yaw pitch`.
+#[derive(Debug, Clone, Default)]
+pub struct CameraPath {
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a keyframe. `time` must not be before the last keyframe's.
+    pub fn push(&mut self, time: f32, camera: Camera) {
+        debug_assert!(time >= self.duration());
+        self.keyframes.push(Keyframe { time, camera })
+    }
+
+    pub fn keyframes(&self) -> &[Keyframe] {
+        &self.keyframes
+    }
+
+    /// Time of the last keyframe.
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |last| last.time)
+    }
+
+    /// Camera at `time`, clamped to the ends of the path, or `None` if the path is empty.
+    pub fn sample(&self, time: f32) -> Option<Camera> {
+        let last = self.keyframes.len().checked_sub(1)?;
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+        if next == 0 {
+            return Some(self.keyframes[0].camera);
+        } else if next > last {
+            return Some(self.keyframes[last].camera);
+        }
+
+        let (a, b) = (&self.keyframes[next - 1], &self.keyframes[next]);
+        let t = if b.time > a.time {
+            (time - a.time) / (b.time - a.time)
+        } else {
+            1.0
+        };
+        // The ends repeat their keyframe as the missing outer control point.
+        let before = self.keyframes[next.saturating_sub(2)].camera;
+        let after = self.keyframes[(next + 1).min(last)].camera;
+
+        // Yaw wraps around, so unwrap it relative to `a` before interpolating.
+        let unwrap = |yaw: f32| {
+            a.camera.yaw()
+                + (yaw - a.camera.yaw() + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+                - std::f32::consts::PI
+        };
+        let b_yaw = unwrap(b.camera.yaw());
+        let before_yaw = unwrap(before.yaw());
+        let after_yaw = b_yaw
+            + (after.yaw() - b_yaw + std::f32::consts::PI).rem_euclid(std::f32::consts::TAU)
+            - std::f32::consts::PI;
+
+        Some(Camera::new(
+            catmull_rom(before.pos, a.camera.pos, b.camera.pos, after.pos, t),
+            catmull_rom(before_yaw, a.camera.yaw(), b_yaw, after_yaw, t)
+                .rem_euclid(std::f32::consts::TAU),
+            catmull_rom(
+                before.pitch(),
+                a.camera.pitch(),
+                b.camera.pitch(),
+                after.pitch(),
+                t,
+            )
+            .clamp(0.0, std::f32::consts::PI),
+        ))
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let mut camera_path = Self::new();
+        for (number, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = || {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("bad keyframe on line {}: {:?}", number + 1, line),
+                )
+            };
+            let values = line
+                .split_whitespace()
+                .map(str::parse::<f32>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid())?;
+            match values[..] {
+                [time, x, y, z, yaw, pitch] if time >= camera_path.duration() => {
+                    camera_path.push(time, Camera::new(uv::Vec3::new(x, y, z), yaw, pitch))
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(camera_path)
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let mut text = String::new();
+        for Keyframe { time, camera } in &self.keyframes {
+            text += &format!(
+                "{} {} {} {} {} {}\n",
+                time,
+                camera.pos.x,
+                camera.pos.y,
+                camera.pos.z,
+                camera.yaw(),
+                camera.pitch()
+            )
+        }
+        std::fs::write(path, text)
+    }
+}
+
+/// Uniform Catmull-Rom segment between `p1` and `p2`.
+fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy
+        + std::ops::Add<Output = T>
+        + std::ops::Sub<Output = T>
+        + std::ops::Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0
+        + (p2 - p0) * t
+        + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+        + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
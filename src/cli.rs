@@ -0,0 +1,164 @@
+use crate::voxel::{Generator, Object};
+
+/// Voxel renderer and editor.
+#[derive(Debug, clap::Parser)]
+#[clap(version)]
+pub struct Cli {
+    /// What to do. Without one, the editor opens a test scene.
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+    #[clap(flatten)]
+    pub client: ClientArgs,
+}
+
+#[derive(Debug, clap::Subcommand)]
+pub enum Command {
+    /// Opens a world in the editor.
+    View { world: std::path::PathBuf },
+    /// Generates a world and saves it.
+    New {
+        world: std::path::PathBuf,
+        /// test, flat or hills.
+        #[clap(long, default_value = "test")]
+        generator: Generator,
+        #[clap(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Rewrites a world in another format. Worlds ending in `.txt` are plain text, anything
+    /// else is binary.
+    Convert {
+        input: std::path::PathBuf,
+        output: std::path::PathBuf,
+    },
+    /// Prints statistics about a world.
+    Info { world: std::path::PathBuf },
+    /// Lists GPUs with their capabilities, numbered for `--gpu`.
+    Gpus,
+}
+
+/// Options for the editor window.
+#[derive(Debug, clap::Args)]
+pub struct ClientArgs {
+    /// Enables the Vulkan validation layers, if installed. Without this or `--no-validation`,
+    /// `STANNOX_VALIDATION=1` or `0` decides, and failing that they're on in debug builds.
+    #[clap(long, global = true)]
+    pub validation: bool,
+    /// Skips the Vulkan validation layers.
+    #[clap(long, global = true, conflicts_with = "validation")]
+    pub no_validation: bool,
+    /// GPU to use, by its number in `stannox gpus` or part of its name.
+    #[clap(long, global = true)]
+    pub gpu: Option<crate::vk::GpuSelector>,
+    /// fifo, fifo-relaxed, mailbox or immediate. Falls back to fifo if unsupported.
+    #[clap(long, global = true, default_value = "mailbox")]
+    pub present_mode: crate::vk::PresentMode,
+    /// Frames recorded ahead of the GPU.
+    #[clap(long, global = true, default_value_t = 2)]
+    pub frames_in_flight: u32,
+    /// Multisampling: 1 (off), 2, 4 or 8 samples per pixel. Lowered if the GPU can't do it.
+    #[clap(long, global = true, default_value_t = 1, parse(try_from_str = parse_samples))]
+    pub msaa: u32,
+    /// Reversed-Z depth, which stops faraway chunks z-fighting.
+    #[clap(long, global = true)]
+    pub reversed_z: bool,
+    /// Caps the frame rate, in frames per second.
+    #[clap(long, global = true)]
+    pub max_fps: Option<f32>,
+    /// Recompiles shaders in `assets/shaders` when they change. Needs the `hot-reload` feature.
+    #[clap(long, global = true)]
+    pub hot_reload: bool,
+    /// Window size like `1280x800`, overriding the settings file.
+    #[clap(long, global = true, parse(try_from_str = parse_window_size))]
+    pub window_size: Option<(u32, u32)>,
+}
+
+impl ClientArgs {
+    /// Environment variable turning validation on (`1`, `true`, `on`) or off (`0`, `false`,
+    /// `off`) when neither flag is given.
+    const VALIDATION_ENV: &'static str = "STANNOX_VALIDATION";
+
+    pub fn instance_options(&self) -> crate::vk::InstanceOptions {
+        crate::vk::InstanceOptions {
+            debug_mode: self.debug_mode(),
+            gpu: self.gpu.clone(),
+            pipeline_cache: dirs::cache_dir()
+                .map(|dir| dir.join("stannox").join("pipeline_cache.bin")),
+        }
+    }
+
+    /// Whether to run Vulkan in debug mode, with validation and logged driver messages.
+    fn debug_mode(&self) -> bool {
+        if self.validation || self.no_validation {
+            return self.validation;
+        }
+        match std::env::var(Self::VALIDATION_ENV).as_deref() {
+            Ok("1" | "true" | "on") => true,
+            Ok("0" | "false" | "off") => false,
+            Ok(value) => {
+                log::warn!("ignoring {}={:?}", Self::VALIDATION_ENV, value);
+                cfg!(debug_assertions)
+            }
+            Err(_) => cfg!(debug_assertions),
+        }
+    }
+}
+
+impl Cli {
+    /// Runs the command, returning a message to report if it fails.
+    pub fn run(self) -> Result<(), String> {
+        match self.command {
+            None => crate::client::run(crate::scene::Scene::new_test(), self.client),
+            Some(Command::View { world }) => {
+                let mut scene = crate::scene::Scene::new();
+                scene.add(load(&world)?, crate::scene::Transform::identity());
+                crate::client::run(scene, self.client)
+            }
+            Some(Command::New {
+                world,
+                generator,
+                seed,
+            }) => save(&generator.generate(seed), &world),
+            Some(Command::Convert { input, output }) => save(&load(&input)?, &output),
+            Some(Command::Info { world }) => {
+                print!("{}", load(&world)?.stats());
+                Ok(())
+            }
+            Some(Command::Gpus) => {
+                let gpus = crate::vk::GpuInfo::list().map_err(|err| err.to_string())?;
+                if gpus.is_empty() {
+                    println!("no GPUs found")
+                }
+                for gpu in gpus {
+                    print!("{}", gpu)
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn load(path: &std::path::Path) -> Result<Object, String> {
+    Object::load(path).map_err(|err| format!("failed to load {}: {}", path.display(), err))
+}
+
+fn save(object: &Object, path: &std::path::Path) -> Result<(), String> {
+    object
+        .save(path)
+        .map_err(|err| format!("failed to save {}: {}", path.display(), err))
+}
+
+fn parse_window_size(text: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("expected a size like 1280x800, got {:?}", text);
+    let (width, height) = text.split_once('x').ok_or_else(invalid)?;
+    match (width.parse(), height.parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+fn parse_samples(text: &str) -> Result<u32, String> {
+    match text.parse() {
+        Ok(samples @ (1 | 2 | 4 | 8)) => Ok(samples),
+        _ => Err(format!("expected 1, 2, 4 or 8, got {:?}", text)),
+    }
+}
@@ -1,34 +1,244 @@
-#[derive(Debug, Copy, Clone)]
+use super::input::Action;
+
+#[derive(Debug, Clone)]
 pub struct ClientCamera {
     camera: crate::camera::Camera,
+    mode: CameraMode,
+    /// Where the view was when the mode last changed, and how far it has moved towards
+    /// `camera` since, from 0 to 1.
+    transition: Option<(crate::camera::Camera, f32)>,
+    /// Path being recorded, and how long it has been recording for.
+    recording: Option<(crate::camera_path::CameraPath, f32)>,
+    settings: super::settings::Settings,
+}
+
+#[derive(Debug, Clone)]
+pub enum CameraMode {
+    /// Free flight through everything.
+    Fly,
+    /// Walking on the world's voxels.
+    Walk(crate::physics::Player),
+    /// Turntable rotation around a pivot, `distance` away.
+    Orbit { target: uv::Vec3, distance: f32 },
+    /// Following a recorded path, ignoring input. Time advances by `fixed_step` each frame
+    /// if set, otherwise by the real frame time.
+    Playback {
+        path: crate::camera_path::CameraPath,
+        time: f32,
+        fixed_step: Option<f32>,
+    },
 }
 
 impl ClientCamera {
-    pub fn new(pos: uv::Vec3, yaw: f32, pitch: f32) -> Self {
+    pub fn new(pos: uv::Vec3, yaw: f32, pitch: f32, settings: super::settings::Settings) -> Self {
         Self {
             camera: crate::camera::Camera::new(pos, yaw, pitch),
+            mode: CameraMode::Fly,
+            transition: None,
+            recording: None,
+            settings,
         }
     }
 
-    const MOVE_KEYS: [winit::event::VirtualKeyCode; 6] = [
-        winit::event::VirtualKeyCode::W,
-        winit::event::VirtualKeyCode::S,
-        winit::event::VirtualKeyCode::A,
-        winit::event::VirtualKeyCode::D,
-        winit::event::VirtualKeyCode::Space,
-        winit::event::VirtualKeyCode::LShift,
+    /// Uses the sensitivity and speeds in `settings` from now on.
+    pub fn set_settings(&mut self, settings: super::settings::Settings) {
+        self.settings = settings
+    }
+
+    const MOVE_ACTIONS: [Action; 6] = [
+        Action::MoveForward,
+        Action::MoveBackward,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUp,
+        Action::MoveDown,
     ];
+    /// Radians turned per pixel of mouse movement, at a sensitivity of 1.
+    const TURN_PER_PIXEL: f32 = 1. / 60.;
+    /// How far ahead the pivot goes when orbiting starts without one.
+    const ORBIT_DISTANCE: f32 = 30.;
+    const MIN_ORBIT_DISTANCE: f32 = 2.;
+    /// Zoom factor per line scrolled or key press.
+    const ZOOM_STEP: f32 = 0.9;
+    const TRANSITION_TIME: f32 = 0.3;
+    /// Time between recorded keyframes. The spline fills in the rest.
+    const RECORD_INTERVAL: f32 = 0.1;
+
+    /// Moves the camera, colliding with `world` when walking. A `pivot` starts orbiting
+    /// around it.
+    pub fn update(
+        &mut self,
+        state: &super::window::ClientState,
+        world: &crate::voxel::Object,
+        pivot: Option<uv::Vec3>,
+    ) {
+        let dt = state.frame_elapsed().as_secs_f32();
+        self.update_mode(state, world, pivot, dt);
+
+        let view = self.camera();
+        if let Some((path, time)) = &mut self.recording {
+            *time += dt;
+            if path.duration() + Self::RECORD_INTERVAL <= *time {
+                path.push(*time, view)
+            }
+        }
+    }
+
+    fn update_mode(
+        &mut self,
+        state: &super::window::ClientState,
+        world: &crate::voxel::Object,
+        pivot: Option<uv::Vec3>,
+        dt: f32,
+    ) {
+        if let CameraMode::Playback {
+            path,
+            time,
+            fixed_step,
+        } = &mut self.mode
+        {
+            *time += fixed_step.unwrap_or(dt);
+            if let Some(camera) = path.sample(*time) {
+                self.camera = camera
+            }
+            if *time >= path.duration() {
+                self.mode = CameraMode::Fly
+            }
+            return;
+        }
+
+        if let Some((_, progress)) = &mut self.transition {
+            *progress += dt / Self::TRANSITION_TIME;
+            if *progress >= 1. {
+                self.transition = None
+            }
+        }
 
-    pub fn update(&mut self, state: &super::window::ClientState) {
-        self.camera.update_orientation(state.mouse_rel() / -60.);
+        if let Some(target) = pivot {
+            let camera = crate::camera::Camera::looking_at(self.camera.pos, target);
+            self.set_mode(
+                CameraMode::Orbit {
+                    target,
+                    distance: (target - self.camera.pos).mag(),
+                },
+                camera,
+            )
+        } else if state.action_pressed(Action::ToggleWalk) {
+            match &self.mode {
+                CameraMode::Walk(_) => self.set_mode(CameraMode::Fly, self.camera),
+                _ => self.set_mode(
+                    CameraMode::Walk(crate::physics::Player::new(
+                        self.camera.pos - uv::Vec3::new(0., 0., crate::physics::Player::EYE_HEIGHT),
+                    )),
+                    self.camera,
+                ),
+            }
+        } else if state.action_pressed(Action::ToggleOrbit) {
+            match &self.mode {
+                CameraMode::Orbit { .. } => self.set_mode(CameraMode::Fly, self.camera),
+                _ => self.set_mode(
+                    CameraMode::Orbit {
+                        target: self.camera.pos + self.camera.look_dir() * Self::ORBIT_DISTANCE,
+                        distance: Self::ORBIT_DISTANCE,
+                    },
+                    self.camera,
+                ),
+            }
+        }
 
-        self.camera.pos += self.camera.move_mat()
-            * state.move_vec(&Self::MOVE_KEYS)
-            * 30.
-            * state.frame_elapsed().as_secs_f32();
+        let move_vec = state.move_vec(&Self::MOVE_ACTIONS);
+        let turn = -state.mouse_rel() * Self::TURN_PER_PIXEL * self.settings.mouse_sensitivity;
+        match &mut self.mode {
+            CameraMode::Fly => {
+                self.camera.update_orientation(turn);
+                self.camera.pos += self.camera.move_mat() * move_vec * self.settings.fly_speed * dt
+            }
+            CameraMode::Walk(player) => {
+                self.camera.update_orientation(turn);
+                let walk = self.camera.move_mat() * uv::Vec3::new(move_vec.x, move_vec.y, 0.);
+                let walk = if walk.mag_sq() > 0. {
+                    walk.normalized() * self.settings.walk_speed
+                } else {
+                    walk
+                };
+                player.update(world, walk.xy(), move_vec.z > 0., dt);
+                self.camera.pos = player.eye()
+            }
+            CameraMode::Orbit { target, distance } => {
+                if state.action_held(Action::Pan) {
+                    // Drag the pivot so the scene follows the mouse.
+                    let pan = state.mouse_rel() * *distance / 500.;
+                    *target += self.camera.up_dir() * pan.y - self.camera.right_dir() * pan.x
+                } else {
+                    self.camera.update_orientation(turn);
+                }
+                let zoom =
+                    state.action_amount(Action::ZoomIn) - state.action_amount(Action::ZoomOut);
+                *distance = (*distance * Self::ZOOM_STEP.powf(zoom)).max(Self::MIN_ORBIT_DISTANCE);
+                self.camera = crate::camera::Camera::orbiting(
+                    *target,
+                    *distance,
+                    self.camera.yaw(),
+                    self.camera.pitch(),
+                )
+            }
+            CameraMode::Playback { .. } => unreachable!(),
+        }
     }
 
+    /// Switches to `mode`, easing the view over from where it is now to `camera`.
+    fn set_mode(&mut self, mode: CameraMode, camera: crate::camera::Camera) {
+        self.transition = Some((self.camera(), 0.));
+        self.mode = mode;
+        self.camera = camera
+    }
+
+    pub fn mode(&self) -> &CameraMode {
+        &self.mode
+    }
+
+    /// Follows `path` from its start, handing control back in fly mode once it ends.
+    pub fn play(&mut self, path: crate::camera_path::CameraPath, fixed_step: Option<f32>) {
+        self.transition = None;
+        self.mode = CameraMode::Playback {
+            path,
+            time: 0.,
+            fixed_step,
+        };
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.mode, CameraMode::Playback { .. })
+    }
+
+    /// Starts recording the view, dropping any recording in progress.
+    pub fn start_recording(&mut self) {
+        let mut path = crate::camera_path::CameraPath::new();
+        path.push(0., self.camera());
+        self.recording = Some((path, 0.))
+    }
+
+    /// Stops recording, returning what was recorded, if anything was.
+    pub fn stop_recording(&mut self) -> Option<crate::camera_path::CameraPath> {
+        let (mut path, time) = self.recording.take()?;
+        if time > path.duration() {
+            path.push(time, self.camera())
+        }
+        Some(path)
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// View to render from, partway through a transition if one is happening.
     pub fn camera(&self) -> crate::camera::Camera {
-        self.camera
+        match self.transition {
+            Some((from, progress)) => {
+                let t = progress * progress * (3. - 2. * progress);
+                from.lerp(&self.camera, t)
+            }
+            None => self.camera,
+        }
     }
 }
@@ -0,0 +1,125 @@
+use super::input::Action;
+use crate::scene::{Scene, SceneHit};
+use crate::voxel::{Axis, Voxel, VoxelBox, VoxelCoord, VoxelRegion};
+
+/// Voxel placing, box selection and clipboard. Edits happen at the voxel under the
+/// crosshair, in the local space of the object it belongs to.
+#[derive(Debug, Default)]
+pub struct Editor {
+    selection: Option<Selection>,
+    clipboard: Option<VoxelRegion>,
+    /// What each edit overwrote, newest last, for undoing.
+    history: std::collections::VecDeque<Snapshot>,
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Selection {
+    object: usize,
+    start: VoxelCoord,
+    end: Option<VoxelCoord>,
+}
+
+#[derive(Debug)]
+struct Snapshot {
+    object: usize,
+    at: VoxelCoord,
+    region: VoxelRegion,
+}
+
+impl Editor {
+    /// Most edits kept for undoing.
+    const HISTORY_LENGTH: usize = 64;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Object the selection is in, and the selected box in its local space.
+    pub fn selection(&self) -> Option<(usize, VoxelBox)> {
+        let selection = self.selection?;
+        Some((
+            selection.object,
+            VoxelBox::new(selection.start, selection.end.unwrap_or(selection.start)),
+        ))
+    }
+
+    /// Handles editing input. Returns the object that changed and the box of voxels in it
+    /// that did, if any.
+    pub fn update(
+        &mut self,
+        state: &super::window::ClientState,
+        scene: &mut Scene,
+        target: Option<SceneHit>,
+    ) -> Option<(usize, VoxelBox)> {
+        if state.action_pressed(Action::Undo) {
+            let Snapshot { object, at, region } = self.history.pop_back()?;
+            region.paste(&mut scene.object_mut(object).object, at, false);
+            return Some((object, region.bounds_at(at)));
+        }
+        if state.action_pressed(Action::ClearSelection) {
+            self.selection = None
+        }
+        if let Some(clipboard) = &self.clipboard {
+            if state.action_pressed(Action::RotateClipboard) {
+                self.clipboard = Some(clipboard.rotated(Axis::Z, 1))
+            } else if state.action_pressed(Action::MirrorClipboard) {
+                self.clipboard = Some(clipboard.mirrored(Axis::X))
+            }
+        }
+
+        if let Some((object, selection)) = self.selection() {
+            if state.action_pressed(Action::Copy) {
+                self.clipboard = Some(VoxelRegion::copy(&scene.object(object).object, selection))
+            } else if state.action_pressed(Action::Cut) {
+                self.remember(scene, object, selection);
+                self.clipboard = Some(VoxelRegion::cut(
+                    &mut scene.object_mut(object).object,
+                    selection,
+                ));
+                return Some((object, selection));
+            }
+        }
+
+        let SceneHit { object, hit } = target?;
+        if state.action_pressed(Action::SelectStart) {
+            self.selection = Some(Selection {
+                object,
+                start: hit.coord,
+                end: None,
+            })
+        } else if state.action_pressed(Action::SelectEnd) {
+            if let Some(selection) = self.selection.as_mut().filter(|s| s.object == object) {
+                selection.end = Some(hit.coord)
+            }
+        } else if state.action_pressed(Action::Paste) {
+            let bounds = self.clipboard.as_ref()?.bounds_at(hit.adjacent());
+            self.remember(scene, object, bounds);
+            let clipboard = self.clipboard.as_ref()?;
+            clipboard.paste(&mut scene.object_mut(object).object, hit.adjacent(), true);
+            return Some((object, bounds));
+        } else if state.action_pressed(Action::Break) {
+            let bounds = VoxelBox::new(hit.coord, hit.coord);
+            self.remember(scene, object, bounds);
+            scene.object_mut(object).object[hit.coord] = Voxel::VOID;
+            return Some((object, bounds));
+        } else if state.action_pressed(Action::Place) {
+            let bounds = VoxelBox::new(hit.adjacent(), hit.adjacent());
+            self.remember(scene, object, bounds);
+            scene.object_mut(object).object[hit.adjacent()] = Voxel::from_id(1);
+            return Some((object, bounds));
+        }
+        None
+    }
+
+    /// Saves what's in `bounds` before an edit overwrites it.
+    fn remember(&mut self, scene: &Scene, object: usize, bounds: VoxelBox) {
+        if self.history.len() == Self::HISTORY_LENGTH {
+            self.history.pop_front();
+        }
+        self.history.push_back(Snapshot {
+            object,
+            at: bounds.min,
+            region: VoxelRegion::copy(&scene.object(object).object, bounds),
+        })
+    }
+}
@@ -0,0 +1,129 @@
+#[cfg(feature = "hot-reload")]
+use crate::shader_compiler::{self, SHADER_DIR};
+
+/// Watches `assets/shaders` and recompiles shaders that change, so pipelines can be rebuilt
+/// without restarting. Needs the `hot-reload` feature, which links shaderc into the client.
+#[cfg(feature = "hot-reload")]
+pub struct ShaderWatcher {
+    compiler: shaderc::Compiler,
+    modified: std::collections::HashMap<std::path::PathBuf, std::time::SystemTime>,
+    last_poll: std::time::Instant,
+}
+
+#[cfg(feature = "hot-reload")]
+impl ShaderWatcher {
+    /// How often the shader directory is checked for changes.
+    const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+    pub fn new() -> Option<Self> {
+        let compiler = match shaderc::Compiler::new() {
+            Some(compiler) => compiler,
+            None => {
+                log::error!("failed to create the shader compiler, hot reloading is off");
+                return None;
+            }
+        };
+        let mut watcher = Self {
+            compiler,
+            modified: std::collections::HashMap::new(),
+            last_poll: std::time::Instant::now(),
+        };
+        // Shaders as they are now are already embedded, so only later changes count.
+        watcher.changed_shaders();
+        log::info!("watching {} for shader changes", SHADER_DIR);
+        Some(watcher)
+    }
+
+    /// Recompiles shaders changed since the last poll, returning the file name and SPIR-V of
+    /// each one and its variants that compiled. Compile errors are logged.
+    pub fn poll(&mut self) -> Vec<(String, Vec<u8>)> {
+        if self.last_poll.elapsed() < Self::POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = std::time::Instant::now();
+        self.changed_shaders()
+            .into_iter()
+            .flat_map(|path| self.compile(&path))
+            .collect()
+    }
+
+    /// Shaders that changed, or every shader if a file they might include changed.
+    fn changed_shaders(&mut self) -> Vec<std::path::PathBuf> {
+        let mut files = Vec::new();
+        if let Err(err) = find_files(std::path::Path::new(SHADER_DIR), &mut files) {
+            log::warn!("failed to read {}: {}", SHADER_DIR, err);
+            return Vec::new();
+        }
+        let mut changed = Vec::new();
+        let mut include_changed = false;
+        for (path, modified) in files {
+            if self.modified.insert(path.clone(), modified) == Some(modified) {
+                continue;
+            }
+            match shader_compiler::shader_kind(&path) {
+                Some(_) => changed.push(path),
+                None => include_changed = true,
+            }
+        }
+        if include_changed {
+            match shader_compiler::shader_paths() {
+                Ok(paths) => changed = paths,
+                Err(err) => log::warn!("failed to read {}: {}", SHADER_DIR, err),
+            }
+        }
+        changed
+    }
+
+    fn compile(&mut self, path: &std::path::Path) -> Vec<(String, Vec<u8>)> {
+        match shader_compiler::compile(&mut self.compiler, path) {
+            Ok(shaders) => shaders
+                .into_iter()
+                .map(|shader| {
+                    if !shader.warnings.is_empty() {
+                        log::warn!("{}", shader.warnings)
+                    }
+                    log::info!("recompiled {}", shader.name);
+                    (shader.name, shader.spirv)
+                })
+                .collect(),
+            Err(err) => {
+                log::error!("failed to compile, keeping the old shader:\n{}", err);
+                Vec::new()
+            }
+        }
+    }
+}
+
+/// Every file under `dir` with when it was modified, including in subdirectories.
+#[cfg(feature = "hot-reload")]
+fn find_files(
+    dir: &std::path::Path,
+    files: &mut Vec<(std::path::PathBuf, std::time::SystemTime)>,
+) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            find_files(&entry.path(), files)?
+        } else if let Ok(modified) = metadata.modified() {
+            files.push((entry.path(), modified))
+        }
+    }
+    Ok(())
+}
+
+/// Stand-in when the client is built without the `hot-reload` feature.
+#[cfg(not(feature = "hot-reload"))]
+pub struct ShaderWatcher;
+
+#[cfg(not(feature = "hot-reload"))]
+impl ShaderWatcher {
+    pub fn new() -> Option<Self> {
+        log::warn!("built without the hot-reload feature, so shaders won't be reloaded");
+        None
+    }
+
+    pub fn poll(&mut self) -> Vec<(String, Vec<u8>)> {
+        Vec::new()
+    }
+}
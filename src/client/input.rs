@@ -0,0 +1,407 @@
+use winit::event::{ModifiersState, MouseButton, VirtualKeyCode};
+
+/// Something the player can do, bound to one or more inputs by an [`InputMap`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Action {
+    MoveForward,
+    MoveBackward,
+    MoveLeft,
+    MoveRight,
+    /// Also jumps while walking.
+    MoveUp,
+    MoveDown,
+    ToggleWalk,
+    ToggleOrbit,
+    /// Double clicked to orbit around the voxel under the crosshair.
+    SetPivot,
+    Pan,
+    ZoomIn,
+    ZoomOut,
+    Break,
+    Place,
+    Undo,
+    SelectStart,
+    SelectEnd,
+    ClearSelection,
+    Copy,
+    Cut,
+    Paste,
+    RotateClipboard,
+    MirrorClipboard,
+    ToggleWireframe,
+    /// Saves the next frame to `screenshots`.
+    Screenshot,
+    /// Rereads the settings file.
+    ReloadSettings,
+    ToggleRecording,
+    Playback,
+    /// Playback at a fixed frame time.
+    PlaybackFixed,
+    /// Frees the cursor from the window.
+    ReleaseMouse,
+    /// Captures the cursor again once it has been released.
+    GrabMouse,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Input {
+    Key(VirtualKeyCode),
+    Button(MouseButton),
+    ScrollUp,
+    ScrollDown,
+}
+
+/// An input together with the modifier keys that have to be down with it.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Binding {
+    pub input: Input,
+    pub modifiers: ModifiersState,
+}
+
+/// Which inputs trigger each action.
+///
+/// Config files have one action per line, followed by `=` and a comma separated list of
+/// bindings, like `copy = Ctrl+C, C`. Keys use winit's `VirtualKeyCode` names, mouse buttons are
+/// `MouseLeft`, `MouseRight` and `MouseMiddle`, and the wheel is `ScrollUp` and `ScrollDown`.
+/// Actions left out keep their default bindings, and `#` starts a comment.
+#[derive(Debug, Clone)]
+pub struct InputMap {
+    bindings: std::collections::HashMap<Action, Vec<Binding>>,
+}
+
+impl Action {
+    pub const ALL: [Self; 31] = [
+        Self::MoveForward,
+        Self::MoveBackward,
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::MoveUp,
+        Self::MoveDown,
+        Self::ToggleWalk,
+        Self::ToggleOrbit,
+        Self::SetPivot,
+        Self::Pan,
+        Self::ZoomIn,
+        Self::ZoomOut,
+        Self::Break,
+        Self::Place,
+        Self::Undo,
+        Self::SelectStart,
+        Self::SelectEnd,
+        Self::ClearSelection,
+        Self::Copy,
+        Self::Cut,
+        Self::Paste,
+        Self::RotateClipboard,
+        Self::MirrorClipboard,
+        Self::ToggleWireframe,
+        Self::Screenshot,
+        Self::ReloadSettings,
+        Self::ToggleRecording,
+        Self::Playback,
+        Self::PlaybackFixed,
+        Self::ReleaseMouse,
+        Self::GrabMouse,
+    ];
+
+    /// Name used in config files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::MoveForward => "move_forward",
+            Self::MoveBackward => "move_backward",
+            Self::MoveLeft => "move_left",
+            Self::MoveRight => "move_right",
+            Self::MoveUp => "move_up",
+            Self::MoveDown => "move_down",
+            Self::ToggleWalk => "toggle_walk",
+            Self::ToggleOrbit => "toggle_orbit",
+            Self::SetPivot => "set_pivot",
+            Self::Pan => "pan",
+            Self::ZoomIn => "zoom_in",
+            Self::ZoomOut => "zoom_out",
+            Self::Break => "break",
+            Self::Place => "place",
+            Self::Undo => "undo",
+            Self::SelectStart => "select_start",
+            Self::SelectEnd => "select_end",
+            Self::ClearSelection => "clear_selection",
+            Self::Copy => "copy",
+            Self::Cut => "cut",
+            Self::Paste => "paste",
+            Self::RotateClipboard => "rotate_clipboard",
+            Self::MirrorClipboard => "mirror_clipboard",
+            Self::ToggleWireframe => "toggle_wireframe",
+            Self::Screenshot => "screenshot",
+            Self::ReloadSettings => "reload_settings",
+            Self::ToggleRecording => "toggle_recording",
+            Self::Playback => "playback",
+            Self::PlaybackFixed => "playback_fixed",
+            Self::ReleaseMouse => "release_mouse",
+            Self::GrabMouse => "grab_mouse",
+        }
+    }
+
+    fn default_bindings(&self) -> &'static str {
+        match self {
+            Self::MoveForward => "W",
+            Self::MoveBackward => "S",
+            Self::MoveLeft => "A",
+            Self::MoveRight => "D",
+            Self::MoveUp => "Space",
+            Self::MoveDown => "LShift",
+            Self::ToggleWalk => "F",
+            Self::ToggleOrbit => "O",
+            Self::SetPivot => "MouseMiddle",
+            Self::Pan => "MouseMiddle",
+            Self::ZoomIn => "ScrollUp",
+            Self::ZoomOut => "ScrollDown",
+            Self::Break => "MouseLeft",
+            Self::Place => "MouseRight",
+            Self::Undo => "Ctrl+Z",
+            Self::SelectStart => "Key1",
+            Self::SelectEnd => "Key2",
+            Self::ClearSelection => "Back",
+            Self::Copy => "C",
+            Self::Cut => "X",
+            Self::Paste => "V",
+            Self::RotateClipboard => "R",
+            Self::MirrorClipboard => "M",
+            Self::ToggleWireframe => "Tab",
+            Self::Screenshot => "F2",
+            Self::ReloadSettings => "F5",
+            Self::ToggleRecording => "F9",
+            Self::Playback => "F10",
+            Self::PlaybackFixed => "Shift+F10",
+            Self::ReleaseMouse => "Escape",
+            Self::GrabMouse => "MouseLeft",
+        }
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|action| action.name() == name)
+            .ok_or_else(|| format!("unknown action {:?}", name))
+    }
+}
+
+impl Input {
+    fn key_from_name(name: &str) -> Option<VirtualKeyCode> {
+        (0..=VirtualKeyCode::Cut as u32)
+            // `VirtualKeyCode` is `repr(u32)` with implicit discriminants, so every value up to
+            // the last variant is a valid key.
+            .map(|code| unsafe { std::mem::transmute::<u32, VirtualKeyCode>(code) })
+            .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name))
+    }
+}
+
+impl std::str::FromStr for Input {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "mouseleft" => Ok(Self::Button(MouseButton::Left)),
+            "mouseright" => Ok(Self::Button(MouseButton::Right)),
+            "mousemiddle" => Ok(Self::Button(MouseButton::Middle)),
+            "scrollup" => Ok(Self::ScrollUp),
+            "scrolldown" => Ok(Self::ScrollDown),
+            _ => Self::key_from_name(name)
+                .map(Self::Key)
+                .ok_or_else(|| format!("unknown input {:?}", name)),
+        }
+    }
+}
+
+impl std::str::FromStr for Binding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parts = text.split('+').map(str::trim).collect::<Vec<_>>();
+        let input = parts.pop().unwrap_or_default().parse()?;
+        let mut modifiers = ModifiersState::empty();
+        for part in parts {
+            modifiers |= match part.to_ascii_lowercase().as_str() {
+                "ctrl" => ModifiersState::CTRL,
+                "shift" => ModifiersState::SHIFT,
+                "alt" => ModifiersState::ALT,
+                "logo" => ModifiersState::LOGO,
+                _ => return Err(format!("unknown modifier {:?}", part)),
+            }
+        }
+        Ok(Self { input, modifiers })
+    }
+}
+
+impl InputMap {
+    /// Default bindings, overridden by the config file at `path` if there is one.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let mut input_map = Self::default();
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(text) => input_map
+                .apply(&text)
+                .map_err(|err| format!("{}: {}", path.display(), err))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => (),
+            Err(err) => return Err(format!("{}: {}", path.display(), err)),
+        }
+        Ok(input_map)
+    }
+
+    /// Rebinds every action listed in `text`, in the config file format.
+    pub fn apply(&mut self, text: &str) -> Result<(), String> {
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let with_line = |err| format!("line {}: {}", number + 1, err);
+            let (action, bindings) = line.split_once('=').ok_or_else(|| {
+                with_line(format!("expected `action = bindings`, got {:?}", line))
+            })?;
+            let action = action.trim().parse::<Action>().map_err(with_line)?;
+            let bindings = bindings
+                .split(',')
+                .map(str::trim)
+                .filter(|binding| !binding.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(with_line)?;
+            self.bindings.insert(action, bindings);
+        }
+        Ok(())
+    }
+
+    /// Whether `binding` is in effect with the `held` modifiers: they include its own, and no
+    /// other binding on the same input asks for more of them. That way `Ctrl+Z` holds back
+    /// `Z`, but holding Shift to move down doesn't stop clicks from working.
+    pub fn is_active(&self, binding: &Binding, held: ModifiersState) -> bool {
+        held.contains(binding.modifiers)
+            && !self.bindings.values().flatten().any(|other| {
+                other.input == binding.input
+                    && held.contains(other.modifiers)
+                    && other.modifiers.contains(binding.modifiers)
+                    && other.modifiers != binding.modifiers
+            })
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map(Vec::as_slice).unwrap_or(&[])
+    }
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            bindings: Action::ALL
+                .iter()
+                .map(|action| {
+                    let bindings = action
+                        .default_bindings()
+                        .split(',')
+                        .map(|binding| binding.parse().unwrap())
+                        .collect();
+                    (*action, bindings)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(input: Input, modifiers: ModifiersState) -> Binding {
+        Binding { input, modifiers }
+    }
+
+    #[test]
+    fn default_bindings_parse() {
+        let input_map = InputMap::default();
+        for action in Action::ALL {
+            assert!(
+                !input_map.bindings(action).is_empty(),
+                "{} has no default binding",
+                action.name()
+            );
+        }
+        assert_eq!(
+            input_map.bindings(Action::Undo),
+            [binding(Input::Key(VirtualKeyCode::Z), ModifiersState::CTRL)]
+        );
+    }
+
+    #[test]
+    fn action_names_round_trip() {
+        for action in Action::ALL {
+            assert_eq!(action.name().parse::<Action>(), Ok(action));
+        }
+    }
+
+    #[test]
+    fn bindings_file_round_trips() {
+        let mut input_map = InputMap::default();
+        input_map
+            .apply(
+                "# comment\n\
+                 \n\
+                 move_forward = Up, W  # trailing comment\n\
+                 undo = Ctrl+Z, ctrl + shift + back\n\
+                 zoom_in = ScrollUp, Equals\n\
+                 break = MouseLeft,\n",
+            )
+            .unwrap();
+        assert_eq!(
+            input_map.bindings(Action::MoveForward),
+            [
+                binding(Input::Key(VirtualKeyCode::Up), ModifiersState::empty()),
+                binding(Input::Key(VirtualKeyCode::W), ModifiersState::empty()),
+            ]
+        );
+        assert_eq!(
+            input_map.bindings(Action::Undo),
+            [
+                binding(Input::Key(VirtualKeyCode::Z), ModifiersState::CTRL),
+                binding(
+                    Input::Key(VirtualKeyCode::Back),
+                    ModifiersState::CTRL | ModifiersState::SHIFT
+                ),
+            ]
+        );
+        assert_eq!(
+            input_map.bindings(Action::ZoomIn),
+            [
+                binding(Input::ScrollUp, ModifiersState::empty()),
+                binding(Input::Key(VirtualKeyCode::Equals), ModifiersState::empty()),
+            ]
+        );
+        assert_eq!(
+            input_map.bindings(Action::Break),
+            [binding(
+                Input::Button(MouseButton::Left),
+                ModifiersState::empty()
+            )]
+        );
+        // Actions left out keep their defaults.
+        assert_eq!(
+            input_map.bindings(Action::Copy),
+            InputMap::default().bindings(Action::Copy)
+        );
+    }
+
+    #[test]
+    fn bindings_file_errors_name_the_line() {
+        let mut input_map = InputMap::default();
+        let err = input_map.apply("undo = Ctrl+Z\nbogus = Q").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{}", err);
+        let err = input_map.apply("undo = Hyper+Z").unwrap_err();
+        assert!(err.starts_with("line 1:"), "{}", err);
+        assert!(input_map.apply("undo Ctrl+Z").is_err());
+        assert!(input_map.apply("undo = NotAKey").is_err());
+    }
+}
@@ -1,44 +1,269 @@
 mod camera;
+mod editor;
+mod hot_reload;
+mod input;
+mod screenshot;
+mod settings;
 mod window;
 
 use crate::vk::Instance;
 use crate::*;
+use input::Action;
 
-pub fn run() -> ! {
-    let event_loop = winit::event_loop::EventLoop::new();
-    let object = voxel::Object::new_test();
-    let window = window::ClientWindow::new(&event_loop);
+/// How far away voxels can be edited from.
+const REACH: f32 = 100.0;
+/// Longest frame physics catches up on, so a stall doesn't turn into a burst of substeps.
+const MAX_PHYSICS_STEP: f32 = 0.1;
+/// Where camera recordings are saved and played back from.
+const CAMERA_PATH_FILE: &str = "camera_path.txt";
+/// Frame time used by [`Action::PlaybackFixed`], for captures that come out the same every
+/// run.
+const PLAYBACK_STEP: f32 = 1.0 / 60.0;
+/// Input bindings, in the format described on [`input::InputMap`].
+const BINDINGS_FILE: &str = "bindings.txt";
 
-    let render_instance = vk::WindowedInstance::new(window.window(), true);
-    let mut render_surface = vk::Swapchain::new(render_instance.clone(), window.size().into());
-    let mut voxel_renderer = vk::VoxelMeshRenderer::new(render_instance.clone(), &render_surface);
-    let mut voxel_manager = vk::VoxelMeshManager::new(render_instance.clone());
+/// Opens the editor on `scene`. Walking collides with its first object. Returns only if the
+/// renderer can't be set up; errors after that are logged and exit the process.
+pub fn run(mut scene: scene::Scene, args: cli::ClientArgs) -> Result<(), String> {
+    let event_loop = winit::event_loop::EventLoop::new();
+    let settings_path = settings::Settings::path();
+    let window_size = args.window_size;
+    // A window size from the command line wins over the settings file, even after reloading.
+    let with_args = move |mut settings: settings::Settings| {
+        if let Some((width, height)) = window_size {
+            settings.window_width = width;
+            settings.window_height = height;
+        }
+        settings
+    };
+    let load_settings = move || match &settings_path {
+        Some(path) => settings::Settings::load(path).map(with_args),
+        None => Ok(with_args(settings::Settings::default())),
+    };
+    let mut settings = load_settings().unwrap_or_else(|err| {
+        log::error!("failed to load settings, using defaults: {}", err);
+        with_args(settings::Settings::default())
+    });
+    let mut window = window::ClientWindow::new(&event_loop, settings.window_size());
+    window.set_max_frame_rate(args.max_fps);
+    let input_map = input::InputMap::load(BINDINGS_FILE).unwrap_or_else(|err| {
+        log::error!("failed to load bindings, using defaults: {}", err);
+        input::InputMap::default()
+    });
 
-    for mesh in &object.fuck_it_mesh_all() {
-        voxel_manager.upload_mesh(mesh)
-    }
+    let render_instance = vk::WindowedInstance::new(window.window(), args.instance_options())
+        .map_err(|err| err.to_string())?;
+    let mut render_surface = vk::Swapchain::new(
+        render_instance.clone(),
+        window.size().into(),
+        vk::SwapchainOptions {
+            present_mode: args.present_mode,
+            frames_in_flight: args.frames_in_flight,
+            samples: args.msaa,
+            reversed_z: args.reversed_z,
+        },
+    )
+    .map_err(|err| err.to_string())?;
+    log::info!("swapchain options: {:?}", render_surface.options());
+    let mut voxel_renderer = vk::VoxelMeshRenderer::new(render_instance.clone(), &render_surface)
+        .map_err(|err| err.to_string())?;
+    voxel_renderer.set_v_fov(settings.fov);
+    let mut selection_renderer =
+        vk::SelectionBoxRenderer::new(render_instance.clone(), &render_surface)
+            .map_err(|err| err.to_string())?;
+    selection_renderer.set_v_fov(settings.fov);
+    let mut voxel_managers = scene
+        .objects()
+        .iter()
+        .map(|scene_object| {
+            let mut voxel_manager = vk::VoxelMeshManager::new(render_instance.clone());
+            for mesh in &scene_object.object.fuck_it_mesh_all() {
+                upload_mesh(&mut voxel_manager, mesh)?
+            }
+            Ok(voxel_manager)
+        })
+        .collect::<vk::Result<Vec<_>>>()
+        .map_err(|err| err.to_string())?;
 
-    println!("{}", voxel_manager.mesh_count());
+    println!(
+        "{}",
+        voxel_managers
+            .iter()
+            .map(|voxel_manager| voxel_manager.mesh_count())
+            .sum::<usize>()
+    );
 
     let mut camera = camera::ClientCamera::new(
         uv::Vec3::new(-90.0, 40.0, 40.0),
         0.0,
         std::f32::consts::FRAC_PI_2,
+        settings,
     );
+    let mut shader_watcher = if args.hot_reload {
+        hot_reload::ShaderWatcher::new()
+    } else {
+        None
+    };
+    let mut editor = editor::Editor::new();
+    let physics = physics::Physics::new();
 
-    window.run(event_loop, move |window, state| {
+    let mut frame = move |window: &window::ClientWindow, state: &window::ClientState| {
         if state.quit() {
-            render_instance.wait_idle();
-            return;
-        }
-        camera.update(state);
-        println!("{:?}", camera);
-        if !render_surface.render(|command_buffer| {
-            voxel_renderer.render(command_buffer, &voxel_manager, &camera.camera());
-        }) {
-            render_instance.wait_idle();
-            render_surface.rebuild(window.size().into());
-            voxel_renderer.rebuild(&render_surface);
+            return render_instance.wait_idle();
         }
-    });
+        if state.action_pressed(Action::ReloadSettings) {
+            match load_settings() {
+                Ok(reloaded) => {
+                    if reloaded.window_size() != settings.window_size() {
+                        window.set_size(reloaded.window_size())
+                    }
+                    settings = reloaded;
+                    camera.set_settings(settings);
+                    voxel_renderer.set_v_fov(settings.fov);
+                    selection_renderer.set_v_fov(settings.fov);
+                    log::info!("reloaded settings");
+                }
+                Err(err) => log::error!("failed to reload settings: {}", err),
+            }
+        }
+        // Double clicking a voxel orbits around it.
+        let pivot = if state.action_double_clicked(Action::SetPivot) {
+            let view = camera.camera();
+            scene
+                .raycast(view.pos, view.look_dir(), REACH)
+                .map(|target| {
+                    scene
+                        .object(target.object)
+                        .transform
+                        .world_point(target.hit.coord.pos())
+                })
+        } else {
+            None
+        };
+        if state.action_pressed(Action::ToggleRecording) {
+            if let Some(path) = camera.stop_recording() {
+                match path.save(CAMERA_PATH_FILE) {
+                    Ok(()) => log::info!("saved camera path to {}", CAMERA_PATH_FILE),
+                    Err(err) => log::error!("failed to save {}: {}", CAMERA_PATH_FILE, err),
+                }
+            } else {
+                camera.start_recording()
+            }
+        } else if (state.action_pressed(Action::Playback)
+            || state.action_pressed(Action::PlaybackFixed))
+            && !camera.is_playing()
+        {
+            match camera_path::CameraPath::load(CAMERA_PATH_FILE) {
+                Ok(path) => camera.play(
+                    path,
+                    Some(PLAYBACK_STEP).filter(|_| state.action_pressed(Action::PlaybackFixed)),
+                ),
+                Err(err) => log::error!("failed to load {}: {}", CAMERA_PATH_FILE, err),
+            }
+        }
+        // Walking only collides with the first object, the world.
+        camera.update(state, &scene.object(0).object, pivot);
+        physics.step(
+            &mut scene,
+            state.frame_elapsed().as_secs_f32().min(MAX_PHYSICS_STEP),
+        );
+
+        let target = scene.raycast(camera.camera().pos, camera.camera().look_dir(), REACH);
+        if let Some((index, changed)) = editor.update(state, &mut scene, target) {
+            render_instance.wait_idle()?;
+            scene.object_mut(index).update_body();
+            let object = &scene.object(index).object;
+            for coord in changed.expanded(1).chunks() {
+                if let Some(mesh) = object.mesh(coord) {
+                    upload_mesh(&mut voxel_managers[index], &mesh)?
+                }
+            }
+        }
+
+        if let Some(watcher) = &mut shader_watcher {
+            let changed = watcher.poll();
+            if !changed.is_empty() {
+                render_instance.wait_idle()?
+            }
+            for (name, spirv) in changed {
+                let voxel = voxel_renderer.set_shader(&name, &spirv, &render_surface);
+                let selection = selection_renderer.set_shader(&name, &spirv, &render_surface);
+                for result in [voxel, selection] {
+                    match result {
+                        Err(err @ vk::Error::DeviceLost { .. }) => return Err(err),
+                        Err(err) => log::error!("failed to reload {}: {}", name, err),
+                        Ok(_) => (),
+                    }
+                }
+            }
+        }
+
+        if state.action_pressed(Action::ToggleWireframe) {
+            render_instance.wait_idle()?;
+            voxel_renderer.set_wireframe(!voxel_renderer.wireframe(), &render_surface)?
+        }
+
+        if state.action_pressed(Action::Screenshot) {
+            render_surface.request_screenshot()
+        }
+
+        let selection = editor.selection();
+        if !render_surface.render(|command_buffer, render_info| {
+            voxel_renderer.render(
+                command_buffer,
+                render_info,
+                voxel_managers
+                    .iter()
+                    .zip(scene.objects())
+                    .map(|(voxel_manager, scene_object)| {
+                        (voxel_manager, scene_object.transform.mat())
+                    }),
+                &camera.camera(),
+            );
+            if let Some((index, selection)) = selection {
+                selection_renderer.render(
+                    command_buffer,
+                    render_info,
+                    selection,
+                    scene.object(index).transform.mat(),
+                    uv::Vec4::new(1.0, 1.0, 1.0, 1.0),
+                    &camera.camera(),
+                )
+            }
+        })? {
+            render_instance.wait_idle()?;
+            render_surface.rebuild(window.size().into())?;
+        }
+        if let Some(screenshot) = render_surface.take_screenshot() {
+            screenshot::save(screenshot)
+        }
+        Ok(())
+    };
+
+    window.run(event_loop, input_map, move |window, state| {
+        if let Err(err) = frame(window, state) {
+            match err {
+                vk::Error::DeviceLost { .. } => {
+                    log::error!("{}. The GPU was reset or its driver crashed.", err)
+                }
+                err => log::error!("{}", err),
+            }
+            std::process::exit(1)
+        }
+    })
+}
+
+/// Uploads a chunk mesh. Running out of memory only loses that chunk, so it's a warning
+/// rather than an error.
+fn upload_mesh<T: Instance>(
+    manager: &mut vk::VoxelMeshManager<T>,
+    mesh: &voxel::Mesh,
+) -> vk::Result<()> {
+    match manager.upload_mesh(mesh) {
+        Err(err @ vk::Error::OutOfMemory { .. }) => {
+            log::warn!("skipping chunk mesh: {}", err);
+            Ok(())
+        }
+        result => result,
+    }
 }
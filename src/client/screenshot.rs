@@ -0,0 +1,225 @@
+use crate::vk::Screenshot;
+
+/// Where screenshots are saved, relative to the working directory.
+const SCREENSHOT_DIR: &str = "screenshots";
+
+/// Writes `screenshot` to a PNG named after the current time on another thread, so converting
+/// and encoding it doesn't hold up the next frame.
+pub fn save(screenshot: Screenshot) {
+    // Colons aren't allowed in file names on Windows.
+    let timestamp = humantime::format_rfc3339_millis(std::time::SystemTime::now())
+        .to_string()
+        .replace(':', "-");
+    let path = std::path::Path::new(SCREENSHOT_DIR).join(format!("{}.png", timestamp));
+    std::thread::spawn(move || {
+        let rgba = to_rgba(screenshot.pixels, screenshot.bgra);
+        let png = encode_png(screenshot.width, screenshot.height, &rgba);
+        let written =
+            std::fs::create_dir_all(SCREENSHOT_DIR).and_then(|()| std::fs::write(&path, png));
+        match written {
+            Ok(()) => log::info!("saved screenshot to {}", path.display()),
+            Err(err) => log::error!("failed to save {}: {}", path.display(), err),
+        }
+    });
+}
+
+/// Puts the channels of 4 byte pixels in RGBA order. Alpha is whatever the clear left behind,
+/// so it's made opaque.
+fn to_rgba(mut pixels: Vec<u8>, bgra: bool) -> Vec<u8> {
+    for pixel in pixels.chunks_exact_mut(4) {
+        if bgra {
+            pixel.swap(0, 2)
+        }
+        pixel[3] = u8::MAX
+    }
+    pixels
+}
+
+/// Encodes an RGBA PNG. The image data is stored without compression, which keeps this simple
+/// at the cost of bigger files.
+fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // 8 bits per channel, RGBA, deflate, adaptive filtering, not interlaced.
+    header.extend_from_slice(&[8, 6, 0, 0, 0]);
+    write_chunk(&mut png, b"IHDR", &header);
+
+    // Each row starts with its filter type, 0 for none.
+    let row_len = 4 * width as usize;
+    let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+    for row in rgba.chunks_exact(row_len.max(1)) {
+        raw.push(0);
+        raw.extend_from_slice(row)
+    }
+    write_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes())
+}
+
+/// Wraps `data` in a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    const MAX_BLOCK: usize = u16::MAX as usize;
+    let mut zlib = Vec::with_capacity(data.len() + data.len() / MAX_BLOCK * 5 + 11);
+    // Deflate with a 32K window and no preset dictionary.
+    zlib.extend_from_slice(&[0x78, 0x01]);
+    let mut blocks = data.chunks(MAX_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xff, 0xff])
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(last as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block)
+    }
+    zlib.extend_from_slice(&adler32(data).to_be_bytes());
+    zlib
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            }
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1, 0);
+    // Sums of this many bytes can't overflow before being reduced.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a
+        }
+        a %= MOD;
+        b %= MOD
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_match_known_values() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+        assert_eq!(adler32(b""), 1);
+    }
+
+    /// Reads back a zlib stream of stored blocks, returning the data and how many blocks held it.
+    fn read_zlib_stored(zlib: &[u8]) -> (Vec<u8>, usize) {
+        assert_eq!(zlib[..2], [0x78, 0x01]);
+        assert_eq!(u16::from_be_bytes([zlib[0], zlib[1]]) % 31, 0);
+        let (mut data, mut blocks, mut at) = (Vec::new(), 0, 2);
+        loop {
+            let header = zlib[at];
+            assert_eq!(header & !1, 0, "not a stored block");
+            let len = u16::from_le_bytes([zlib[at + 1], zlib[at + 2]]);
+            let nlen = u16::from_le_bytes([zlib[at + 3], zlib[at + 4]]);
+            assert_eq!(nlen, !len);
+            data.extend_from_slice(&zlib[at + 5..at + 5 + len as usize]);
+            at += 5 + len as usize;
+            blocks += 1;
+            if header & 1 == 1 {
+                break;
+            }
+        }
+        assert_eq!(zlib[at..], adler32(&data).to_be_bytes());
+        (data, blocks)
+    }
+
+    #[test]
+    fn stored_blocks_split_at_65535_bytes() {
+        for (len, blocks) in [(0, 1), (1, 1), (65535, 1), (65536, 2), (2 * 65535 + 1, 3)] {
+            let data = (0..len).map(|i| (i % 253) as u8).collect::<Vec<_>>();
+            let zlib = zlib_stored(&data);
+            assert_eq!(zlib.len(), 2 + 5 * blocks + len + 4, "{} bytes", len);
+            assert_eq!(read_zlib_stored(&zlib), (data, blocks), "{} bytes", len);
+        }
+    }
+
+    /// Splits a PNG into its chunks' types and data, checking the signature and each CRC.
+    fn chunks(png: &[u8]) -> Vec<([u8; 4], &[u8])> {
+        assert_eq!(png[..8], *b"\x89PNG\r\n\x1a\n");
+        let (mut chunks, mut at) = (Vec::new(), 8);
+        while at < png.len() {
+            let len = u32::from_be_bytes([png[at], png[at + 1], png[at + 2], png[at + 3]]) as usize;
+            let kind = [png[at + 4], png[at + 5], png[at + 6], png[at + 7]];
+            let data = &png[at + 8..at + 8 + len];
+            let crc = &png[at + 8 + len..at + 12 + len];
+            assert_eq!(crc, crc32(&png[at + 4..at + 8 + len]).to_be_bytes());
+            chunks.push((kind, data));
+            at += 12 + len
+        }
+        chunks
+    }
+
+    fn check_png(width: u32, height: u32) -> usize {
+        let rgba = (0..4 * width * height)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        let png = encode_png(width, height, &rgba);
+        let chunks = chunks(&png);
+        let kinds = chunks.iter().map(|(kind, _)| kind).collect::<Vec<_>>();
+        assert_eq!(kinds, [b"IHDR", b"IDAT", b"IEND"]);
+
+        let mut header = width.to_be_bytes().to_vec();
+        header.extend_from_slice(&height.to_be_bytes());
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+        assert_eq!(chunks[0].1, &header[..]);
+        assert!(chunks[2].1.is_empty());
+
+        let (raw, blocks) = read_zlib_stored(chunks[1].1);
+        let row_len = 4 * width as usize;
+        assert_eq!(raw.len(), (row_len + 1) * height as usize);
+        for (row, pixels) in raw
+            .chunks_exact(row_len + 1)
+            .zip(rgba.chunks_exact(row_len))
+        {
+            assert_eq!(row[0], 0);
+            assert_eq!(&row[1..], pixels)
+        }
+        blocks
+    }
+
+    #[test]
+    fn png_layout() {
+        assert_eq!(check_png(1, 1), 1);
+        assert!(check_png(300, 300) > 1);
+    }
+
+    #[test]
+    fn bgra_is_swizzled_and_made_opaque() {
+        assert_eq!(
+            to_rgba(vec![1, 2, 3, 0, 5, 6, 7, 8], true),
+            [3, 2, 1, 255, 7, 6, 5, 255]
+        );
+        assert_eq!(to_rgba(vec![1, 2, 3, 0], false), [1, 2, 3, 255]);
+    }
+}
@@ -0,0 +1,94 @@
+/// Player preferences, read from `settings.toml` in the user's config directory.
+///
+/// Every field is optional in the file, and missing ones keep their defaults:
+///
+/// ```toml
+/// mouse_sensitivity = 1.0
+/// fly_speed = 30.0
+/// walk_speed = 6.0
+/// fov = 60.0
+/// window_width = 1280
+/// window_height = 800
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Multiplier on how far the view turns per pixel of mouse movement.
+    pub mouse_sensitivity: f32,
+    /// Voxels per second in fly mode.
+    pub fly_speed: f32,
+    /// Voxels per second in walk mode.
+    pub walk_speed: f32,
+    /// Vertical field of view, in degrees.
+    pub fov: f32,
+    /// Window size in logical pixels.
+    pub window_width: u32,
+    pub window_height: u32,
+}
+
+impl Settings {
+    /// `stannox/settings.toml` in the platform's config directory, if it has one.
+    pub fn path() -> Option<std::path::PathBuf> {
+        Some(dirs::config_dir()?.join("stannox").join("settings.toml"))
+    }
+
+    /// Default settings, overridden by the file at `path` if there is one.
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text).map_err(|err| format!("{}: {}", path.display(), err)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(format!("{}: {}", path.display(), err)),
+        }
+    }
+
+    /// Settings from the TOML in `text`, checked to be in range.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let settings = toml::from_str::<Self>(text).map_err(|err| err.to_string())?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let positive = [
+            ("mouse_sensitivity", self.mouse_sensitivity),
+            ("fly_speed", self.fly_speed),
+            ("walk_speed", self.walk_speed),
+        ];
+        for (name, value) in positive.iter() {
+            if !(*value > 0.0 && value.is_finite()) {
+                return Err(format!("{} must be positive, got {}", name, value));
+            }
+        }
+        if !(1.0..=179.0).contains(&self.fov) {
+            return Err(format!(
+                "fov must be between 1 and 179 degrees, got {}",
+                self.fov
+            ));
+        }
+        if self.window_width == 0 || self.window_height == 0 {
+            return Err(format!(
+                "window size must not be zero, got {}x{}",
+                self.window_width, self.window_height
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn window_size(&self) -> winit::dpi::LogicalSize<u32> {
+        winit::dpi::LogicalSize::new(self.window_width, self.window_height)
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: 1.0,
+            fly_speed: 30.0,
+            walk_speed: 6.0,
+            fov: 60.0,
+            window_width: 1280,
+            window_height: 800,
+        }
+    }
+}
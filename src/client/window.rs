@@ -1,5 +1,9 @@
+use super::input::{Action, Input, InputMap};
+
 pub struct ClientWindow {
     window: winit::window::Window,
+    /// Shortest time between frames, if the frame rate is capped.
+    min_frame_time: Option<std::time::Duration>,
 }
 
 pub struct ClientState {
@@ -11,9 +15,18 @@ pub struct ClientState {
     frame_elapsed: std::time::Duration,
 
     mouse_rel: uv::Vec2,
+    scroll: f32,
     input_mode: InputMode,
+    input_map: InputMap,
+    modifiers: winit::event::ModifiersState,
 
     key_held: [bool; 255],
+    key_pressed: [bool; 255],
+    button_held: [bool; 3],
+    button_pressed: [bool; 3],
+    button_double_clicked: [bool; 3],
+    /// When each button was last clicked, if that click could still start a double click.
+    last_click: [Option<std::time::Instant>; 3],
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
@@ -23,14 +36,28 @@ enum InputMode {
 }
 
 impl ClientWindow {
-    pub fn new(event_loop: &winit::event_loop::EventLoop<()>) -> Self {
+    pub fn new(
+        event_loop: &winit::event_loop::EventLoop<()>,
+        size: winit::dpi::LogicalSize<u32>,
+    ) -> Self {
         let window = winit::window::WindowBuilder::new()
-            .with_inner_size(winit::dpi::LogicalSize::new(1280, 800))
+            .with_inner_size(size)
             .with_title("voxel render demo")
             .build(event_loop)
             .unwrap();
 
-        Self { window }
+        Self {
+            window,
+            min_frame_time: None,
+        }
+    }
+
+    /// Caps how many frames are run per second. `None` runs them as fast as presenting
+    /// allows.
+    pub fn set_max_frame_rate(&mut self, max_frame_rate: Option<f32>) {
+        self.min_frame_time = max_frame_rate
+            .filter(|rate| *rate > 0.0)
+            .map(|rate| std::time::Duration::from_secs_f32(1.0 / rate))
     }
 
     pub fn window(&self) -> &winit::window::Window {
@@ -41,6 +68,10 @@ impl ClientWindow {
         self.window.inner_size()
     }
 
+    pub fn set_size(&self, size: winit::dpi::LogicalSize<u32>) {
+        self.window.set_inner_size(size)
+    }
+
     pub fn center(&self) -> winit::dpi::PhysicalPosition<u32> {
         let size = self.window.inner_size();
         winit::dpi::PhysicalPosition::new(size.width / 2, size.height / 2)
@@ -63,10 +94,21 @@ impl ClientWindow {
     pub fn run<Fn: 'static + FnMut(&Self, &ClientState)>(
         mut self,
         event_loop: winit::event_loop::EventLoop<()>,
+        input_map: InputMap,
         mut input_handler: Fn,
     ) -> ! {
-        let mut state = ClientState::new(&mut self);
+        let mut state = ClientState::new(&mut self, input_map);
         event_loop.run(move |event, _, control_flow| {
+            if let winit::event::Event::MainEventsCleared = event {
+                let next_frame = self.min_frame_time.map(|time| state.time + time);
+                match next_frame {
+                    Some(next_frame) if std::time::Instant::now() < next_frame => {
+                        *control_flow = winit::event_loop::ControlFlow::WaitUntil(next_frame);
+                        return;
+                    }
+                    _ => *control_flow = winit::event_loop::ControlFlow::Poll,
+                }
+            }
             state.handle_event(&mut self, event);
             if state.quit() {
                 *control_flow = winit::event_loop::ControlFlow::Exit
@@ -80,7 +122,10 @@ impl ClientWindow {
 }
 
 impl ClientState {
-    pub fn new(window: &mut ClientWindow) -> Self {
+    /// Longest gap between the two clicks of a double click.
+    const DOUBLE_CLICK_TIME: std::time::Duration = std::time::Duration::from_millis(300);
+
+    pub fn new(window: &mut ClientWindow, input_map: InputMap) -> Self {
         let now = std::time::Instant::now();
         let input_mode = InputMode::Camera;
         window.set_input_mode(input_mode);
@@ -91,8 +136,16 @@ impl ClientState {
             time: now,
             frame_elapsed: Default::default(),
             mouse_rel: uv::Vec2::zero(),
+            scroll: 0.0,
             input_mode,
+            input_map,
+            modifiers: winit::event::ModifiersState::empty(),
             key_held: [false; 255],
+            key_pressed: [false; 255],
+            button_held: [false; 3],
+            button_pressed: [false; 3],
+            button_double_clicked: [false; 3],
+            last_click: [None; 3],
         }
     }
 
@@ -108,23 +161,60 @@ impl ClientState {
             winit::event::Event::WindowEvent { event, .. } => match event {
                 winit::event::WindowEvent::Destroyed
                 | winit::event::WindowEvent::CloseRequested => self.quit = true,
+                winit::event::WindowEvent::ModifiersChanged(modifiers) => {
+                    self.modifiers = modifiers
+                }
                 winit::event::WindowEvent::KeyboardInput { input, .. } => {
                     if let Some(keycode) = input.virtual_keycode {
-                        if keycode == winit::event::VirtualKeyCode::Escape
-                            && self.input_mode == InputMode::Camera
-                        {
-                            self.set_input_mode(window, InputMode::Mouse)
+                        let pressed = input.state == winit::event::ElementState::Pressed;
+                        if pressed && !self.key_held[keycode as usize] {
+                            self.key_pressed[keycode as usize] = true;
+                            self.switch_input_mode(window, Input::Key(keycode))
                         }
-                        self.key_held[keycode as usize] =
-                            input.state == winit::event::ElementState::Pressed
+                        self.key_held[keycode as usize] = pressed
                     }
                 }
-                winit::event::WindowEvent::MouseInput { state, button, .. } => {
-                    if button == winit::event::MouseButton::Left
-                        && state == winit::event::ElementState::Pressed
-                        && self.input_mode == InputMode::Mouse
-                    {
-                        self.set_input_mode(window, InputMode::Camera)
+                winit::event::WindowEvent::MouseInput {
+                    state: winit::event::ElementState::Pressed,
+                    button,
+                    ..
+                } => {
+                    if self.input_mode == InputMode::Camera {
+                        self.switch_input_mode(window, Input::Button(button));
+                        if let Some(index) = Self::button_index(button) {
+                            self.button_held[index] = true;
+                            self.button_pressed[index] = true;
+                            // Timed when the click arrives, since frames can be far apart.
+                            let now = std::time::Instant::now();
+                            match self.last_click[index] {
+                                Some(last) if now - last <= Self::DOUBLE_CLICK_TIME => {
+                                    self.button_double_clicked[index] = true;
+                                    self.last_click[index] = None
+                                }
+                                _ => self.last_click[index] = Some(now),
+                            }
+                        }
+                    } else {
+                        self.switch_input_mode(window, Input::Button(button))
+                    }
+                }
+                winit::event::WindowEvent::MouseInput {
+                    state: winit::event::ElementState::Released,
+                    button,
+                    ..
+                } => {
+                    if let Some(index) = Self::button_index(button) {
+                        self.button_held[index] = false
+                    }
+                }
+                winit::event::WindowEvent::MouseWheel { delta, .. }
+                    if self.input_mode == InputMode::Camera =>
+                {
+                    self.scroll += match delta {
+                        winit::event::MouseScrollDelta::LineDelta(_, y) => y,
+                        winit::event::MouseScrollDelta::PixelDelta(position) => {
+                            position.y as f32 / 20.0
+                        }
                     }
                 }
                 _ => (),
@@ -132,8 +222,9 @@ impl ClientState {
             winit::event::Event::DeviceEvent { event, .. } => match event {
                 winit::event::DeviceEvent::MouseMotion { delta } => {
                     if self.input_mode == InputMode::Camera {
-                        self.mouse_rel.x = delta.0 as f32;
-                        self.mouse_rel.y = delta.1 as f32
+                        // Several motions can arrive between frames, especially with a frame cap.
+                        self.mouse_rel.x += delta.0 as f32;
+                        self.mouse_rel.y += delta.1 as f32
                     }
                 }
                 _ => (),
@@ -142,17 +233,40 @@ impl ClientState {
         }
     }
 
-    pub fn move_vec(&self, move_keys: &[winit::event::VirtualKeyCode; 6]) -> uv::Vec3 {
+    /// Axes from pairs of opposing actions: forward/backward, left/right and up/down.
+    pub fn move_vec(&self, move_actions: &[Action; 6]) -> uv::Vec3 {
+        let axis = |positive, negative| {
+            (self.action_held(positive) as i32 - self.action_held(negative) as i32) as f32
+        };
         match self.input_mode {
             InputMode::Camera => uv::Vec3::new(
-                (self.key_held(move_keys[0]) as i32 - self.key_held(move_keys[1]) as i32) as f32,
-                (self.key_held(move_keys[2]) as i32 - self.key_held(move_keys[3]) as i32) as f32,
-                (self.key_held(move_keys[4]) as i32 - self.key_held(move_keys[5]) as i32) as f32,
+                axis(move_actions[0], move_actions[1]),
+                axis(move_actions[2], move_actions[3]),
+                axis(move_actions[4], move_actions[5]),
             ),
             InputMode::Mouse => uv::Vec3::zero(),
         }
     }
 
+    /// Grabs or releases the mouse if `input`, which just went down, is bound to do that.
+    fn switch_input_mode(&mut self, window: &mut ClientWindow, input: Input) {
+        match self.input_mode {
+            InputMode::Camera if self.triggers(Action::ReleaseMouse, input) => {
+                self.set_input_mode(window, InputMode::Mouse)
+            }
+            InputMode::Mouse if self.triggers(Action::GrabMouse, input) => {
+                self.set_input_mode(window, InputMode::Camera)
+            }
+            _ => (),
+        }
+    }
+
+    fn triggers(&self, action: Action, input: Input) -> bool {
+        self.input_map.bindings(action).iter().any(|binding| {
+            binding.input == input && self.input_map.is_active(binding, self.modifiers)
+        })
+    }
+
     fn set_input_mode(&mut self, window: &mut ClientWindow, input_mode: InputMode) {
         self.input_mode = input_mode;
         window.set_input_mode(input_mode)
@@ -160,7 +274,11 @@ impl ClientState {
 
     pub fn reset(&mut self) {
         self.main = false;
-        self.mouse_rel = uv::Vec2::zero()
+        self.mouse_rel = uv::Vec2::zero();
+        self.scroll = 0.0;
+        self.key_pressed = [false; 255];
+        self.button_pressed = [false; 3];
+        self.button_double_clicked = [false; 3]
     }
 
     fn main(&self) -> bool {
@@ -181,7 +299,80 @@ impl ClientState {
         self.mouse_rel
     }
 
-    pub fn key_held(&self, key: winit::event::VirtualKeyCode) -> bool {
-        self.key_held[key as usize]
+    /// Whether an input bound to `action` is down.
+    pub fn action_held(&self, action: Action) -> bool {
+        self.active_inputs(action)
+            .any(|input| self.input_held(input))
+    }
+
+    /// Whether an input bound to `action` went down since the last frame.
+    pub fn action_pressed(&self, action: Action) -> bool {
+        self.active_inputs(action)
+            .any(|input| self.input_pressed(input))
+    }
+
+    /// Whether a mouse button bound to `action` finished a double click since the last frame.
+    pub fn action_double_clicked(&self, action: Action) -> bool {
+        self.active_inputs(action).any(|input| match input {
+            Input::Button(button) => match Self::button_index(button) {
+                Some(index) => self.button_double_clicked[index],
+                None => false,
+            },
+            _ => false,
+        })
+    }
+
+    /// How much `action` happened since the last frame: lines scrolled for scroll wheel
+    /// bindings, and one per other binding that was pressed.
+    pub fn action_amount(&self, action: Action) -> f32 {
+        self.active_inputs(action)
+            .map(|input| match input {
+                Input::ScrollUp => self.scroll.max(0.0),
+                Input::ScrollDown => (-self.scroll).max(0.0),
+                _ => self.input_pressed(input) as i32 as f32,
+            })
+            .sum()
+    }
+
+    /// Inputs bound to `action` whose modifiers are down.
+    fn active_inputs(&self, action: Action) -> impl Iterator<Item = Input> + '_ {
+        self.input_map
+            .bindings(action)
+            .iter()
+            .filter(move |binding| self.input_map.is_active(binding, self.modifiers))
+            .map(|binding| binding.input)
+    }
+
+    fn input_held(&self, input: Input) -> bool {
+        match input {
+            Input::Key(key) => self.key_held[key as usize],
+            Input::Button(button) => {
+                matches!(Self::button_index(button), Some(index) if self.button_held[index])
+            }
+            Input::ScrollUp => self.scroll > 0.0,
+            Input::ScrollDown => self.scroll < 0.0,
+        }
+    }
+
+    /// Whether `input` went down since the last frame. Mouse buttons only count while the
+    /// camera has the mouse.
+    fn input_pressed(&self, input: Input) -> bool {
+        match input {
+            Input::Key(key) => self.key_pressed[key as usize],
+            Input::Button(button) => {
+                matches!(Self::button_index(button), Some(index) if self.button_pressed[index])
+            }
+            Input::ScrollUp => self.scroll > 0.0,
+            Input::ScrollDown => self.scroll < 0.0,
+        }
+    }
+
+    fn button_index(button: winit::event::MouseButton) -> Option<usize> {
+        match button {
+            winit::event::MouseButton::Left => Some(0),
+            winit::event::MouseButton::Right => Some(1),
+            winit::event::MouseButton::Middle => Some(2),
+            winit::event::MouseButton::Other(_) => None,
+        }
     }
 }
@@ -1,11 +1,20 @@
 #![allow(dead_code)]
 
 mod camera;
+mod camera_path;
+mod cli;
 mod client;
+mod physics;
+mod scene;
+#[cfg(any(test, feature = "hot-reload"))]
+mod shader_compiler;
 mod vk;
 mod voxel;
 
 fn main() {
     env_logger::init();
-    client::run()
+    if let Err(err) = <cli::Cli as clap::Parser>::parse().run() {
+        eprintln!("error: {}", err);
+        std::process::exit(1)
+    }
 }
@@ -0,0 +1,151 @@
+use crate::scene::Transform;
+use crate::voxel::{Direction, Object, VoxelCoord};
+
+/// Mass distribution of an object, with every solid voxel a unit cube of `density`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MassProperties {
+    pub mass: f32,
+    /// In object-local voxel coordinates.
+    pub center_of_mass: uv::Vec3,
+    /// Inertia tensor about the center of mass, in the object's rotated frame.
+    pub inertia: uv::Mat3,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RigidBody {
+    pub mass_properties: MassProperties,
+    pub velocity: uv::Vec3,
+    /// Axis times angular speed in radians per second, in world space.
+    pub angular_velocity: uv::Vec3,
+    /// Solid voxels with at least one void neighbor, which are all contacts need to look at.
+    surface: Vec<VoxelCoord>,
+}
+
+impl MassProperties {
+    /// Mass properties of `object` at the given scale, or `None` if it has no solid voxels.
+    pub fn from_object(object: &Object, density: f32, scale: f32) -> Option<Self> {
+        // Sum up unit masses and their first and second moments about the local origin,
+        // then shift the second moment to the center of mass.
+        let mut count = 0.0;
+        let mut sum = uv::Vec3::zero();
+        let mut sum_sq = [[0.0f32; 3]; 3];
+        for (coord, _) in object.solid_voxels() {
+            let pos = coord.pos();
+            count += 1.0;
+            sum += pos;
+            for (i, row) in sum_sq.iter_mut().enumerate() {
+                for (j, entry) in row.iter_mut().enumerate() {
+                    *entry += pos[i] * pos[j]
+                }
+            }
+        }
+        if count == 0.0 {
+            return None;
+        }
+
+        let center_of_mass = sum / count;
+        let voxel_mass = density * scale * scale * scale;
+        // Second moments about the center of mass, plus each cube's own 1/12 per axis.
+        let moment = |i: usize, j: usize| {
+            let central = sum_sq[i][j] / count - center_of_mass[i] * center_of_mass[j];
+            central + if i == j { 1.0 / 12.0 } else { 0.0 }
+        };
+        let trace = moment(0, 0) + moment(1, 1) + moment(2, 2);
+        let mut inertia = uv::Mat3::identity();
+        for i in 0..3 {
+            for j in 0..3 {
+                let value = if i == j { trace } else { 0.0 } - moment(i, j);
+                inertia.cols[j][i] = value * count * voxel_mass * scale * scale
+            }
+        }
+
+        Some(Self {
+            mass: count * voxel_mass,
+            center_of_mass,
+            inertia,
+        })
+    }
+}
+
+impl RigidBody {
+    /// Body at rest for `object`, or `None` if it has no solid voxels.
+    pub fn new(object: &Object, density: f32, scale: f32) -> Option<Self> {
+        Some(Self {
+            mass_properties: MassProperties::from_object(object, density, scale)?,
+            velocity: uv::Vec3::zero(),
+            angular_velocity: uv::Vec3::zero(),
+            surface: surface(object),
+        })
+    }
+
+    /// Recomputes everything derived from the voxels after `object` changed, keeping the
+    /// velocities. Returns `false` if the object no longer has any solid voxels.
+    pub fn update(&mut self, object: &Object, density: f32, scale: f32) -> bool {
+        match MassProperties::from_object(object, density, scale) {
+            Some(mass_properties) => {
+                self.mass_properties = mass_properties;
+                self.surface = surface(object);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn surface(&self) -> &[VoxelCoord] {
+        &self.surface
+    }
+
+    pub fn inverse_mass(&self) -> f32 {
+        1.0 / self.mass_properties.mass
+    }
+
+    /// Inverse inertia tensor in world space, for the body in `transform`.
+    pub fn inverse_inertia(&self, transform: &Transform) -> uv::Mat3 {
+        let rotation = transform.rotation.into_matrix();
+        rotation * self.mass_properties.inertia.inversed() * rotation.transposed()
+    }
+
+    /// World-space position of the center of mass.
+    pub fn center_of_mass(&self, transform: &Transform) -> uv::Vec3 {
+        transform.world_point(self.mass_properties.center_of_mass)
+    }
+
+    /// Velocity of the world-space `point`, assumed attached to the body.
+    pub fn point_velocity(&self, transform: &Transform, point: uv::Vec3) -> uv::Vec3 {
+        self.velocity
+            + self
+                .angular_velocity
+                .cross(point - self.center_of_mass(transform))
+    }
+
+    pub fn apply_impulse(&mut self, transform: &Transform, impulse: uv::Vec3, point: uv::Vec3) {
+        let arm = point - self.center_of_mass(transform);
+        self.velocity += impulse * self.inverse_mass();
+        self.angular_velocity += self.inverse_inertia(transform) * arm.cross(impulse)
+    }
+
+    /// Moves `transform` along with the body's velocities for `dt` seconds, rotating about
+    /// the center of mass.
+    pub fn integrate(&self, transform: &mut Transform, dt: f32) {
+        let center_of_mass = self.center_of_mass(transform) + self.velocity * dt;
+        let angle = self.angular_velocity.mag() * dt;
+        if angle > 0.0 {
+            let axis = self.angular_velocity.normalized();
+            let rotation =
+                uv::Rotor3::from_angle_plane(angle, uv::Bivec3::from_normalized_axis(axis));
+            transform.rotation = (rotation * transform.rotation).normalized()
+        }
+        transform.position = center_of_mass
+            - transform.rotation * (self.mass_properties.center_of_mass * transform.scale)
+    }
+}
+
+fn surface(object: &Object) -> Vec<VoxelCoord> {
+    object
+        .solid_voxels()
+        .map(|(coord, _)| coord)
+        .filter(|coord| {
+            Direction::iterate().any(|direction| object[coord.advance(direction)].is_void())
+        })
+        .collect()
+}
@@ -0,0 +1,177 @@
+use super::RigidBody;
+use crate::scene::{SceneObject, Transform};
+use crate::voxel::{Direction, VoxelCoord};
+
+/// Overlap between two objects, merged into a single point.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Contact {
+    /// World-space average of the overlapping voxels.
+    pub point: uv::Vec3,
+    /// World-space direction to push the first object out of the second.
+    pub normal: uv::Vec3,
+    /// How deep the deepest voxel center of the first object is inside the second, in world
+    /// units. Objects at rest overlap by about half a voxel.
+    pub depth: f32,
+}
+
+/// World-space bounding box of an object's allocated chunks, as (min, max).
+fn world_bounds(scene_object: &SceneObject) -> Option<(uv::Vec3, uv::Vec3)> {
+    let bounds = scene_object.object.chunk_bounds()?;
+    let (min, max) = (
+        bounds.min.pos() - uv::Vec3::broadcast(0.5),
+        bounds.max.pos() + uv::Vec3::broadcast(0.5),
+    );
+    let mut world_min = uv::Vec3::broadcast(f32::INFINITY);
+    let mut world_max = uv::Vec3::broadcast(f32::NEG_INFINITY);
+    for corner in 0..8 {
+        let local = uv::Vec3::new(
+            if corner & 1 == 0 { min.x } else { max.x },
+            if corner & 2 == 0 { min.y } else { max.y },
+            if corner & 4 == 0 { min.z } else { max.z },
+        );
+        let world = scene_object.transform.world_point(local);
+        world_min = world_min.min_by_component(world);
+        world_max = world_max.max_by_component(world);
+    }
+    Some((world_min, world_max))
+}
+
+/// Tests every surface voxel of `a`, which must have a body, against the voxel grid of `b`. Only
+/// voxel centers of `a` are tested, so thin overlaps along edges can go unnoticed, but that's
+/// plenty to keep objects from sinking into each other.
+pub fn find(a: &SceneObject, b: &SceneObject) -> Option<Contact> {
+    let surface = a.body.as_ref()?.surface();
+    let (a_min, a_max) = world_bounds(a)?;
+    let (b_min, b_max) = world_bounds(b)?;
+    if (0..3).any(|axis| a_max[axis] < b_min[axis] || b_max[axis] < a_min[axis]) {
+        return None;
+    }
+
+    let mut count = 0.0;
+    let mut point = uv::Vec3::zero();
+    let mut normal = uv::Vec3::zero();
+    let mut depth = 0.0f32;
+    for coord in surface {
+        let world = a.transform.world_point(coord.pos());
+        if (0..3).any(|axis| world[axis] < b_min[axis] || world[axis] > b_max[axis]) {
+            continue;
+        }
+        let local = b.transform.local_point(world);
+        let b_coord = VoxelCoord::containing(local);
+        if b.object[b_coord].is_void() {
+            continue;
+        }
+
+        // Push out through whichever open faces the overlapped voxel of `b` has. A voxel
+        // buried inside `b` has none, so fall back to pushing straight apart.
+        let mut local_normal = uv::Vec3::zero();
+        for direction in Direction::iterate() {
+            if b.object[b_coord.advance(direction)].is_void() {
+                let vec = direction.vec();
+                local_normal += uv::Vec3::new(vec.x as f32, vec.y as f32, vec.z as f32)
+            }
+        }
+        let voxel_normal = if local_normal.mag_sq() > 0.0 {
+            b.transform.world_dir(local_normal.normalized())
+        } else {
+            (world - b.transform.world_point(b_coord.pos())).normalized()
+        };
+        if !voxel_normal.x.is_finite() {
+            continue;
+        }
+        let offset = (local - b_coord.pos()).dot(b.transform.local_dir(voxel_normal));
+        let voxel_depth = (0.5 - offset) * b.transform.scale;
+
+        count += 1.0;
+        point += world;
+        normal += voxel_normal;
+        depth = depth.max(voxel_depth)
+    }
+
+    if count == 0.0 || normal.mag_sq() == 0.0 {
+        return None;
+    }
+    Some(Contact {
+        point: point / count,
+        normal: normal.normalized(),
+        depth,
+    })
+}
+
+/// Applies a collision impulse with restitution and Coulomb friction at `contact`, and nudges
+/// the objects apart to undo the overlap. `b` is treated as immovable if it has no body.
+pub fn resolve(
+    a: (&mut Transform, &mut RigidBody),
+    b: (&mut Transform, Option<&mut RigidBody>),
+    contact: Contact,
+    restitution: f32,
+    friction: f32,
+) {
+    let (a_transform, a_body) = a;
+    let (b_transform, mut b_body) = b;
+    let Contact {
+        point,
+        normal,
+        depth,
+    } = contact;
+
+    let b_velocity = match &b_body {
+        Some(body) => body.point_velocity(b_transform, point),
+        None => uv::Vec3::zero(),
+    };
+    let relative = a_body.point_velocity(a_transform, point) - b_velocity;
+    // Inverse effective mass of the pair along `direction` at the contact point.
+    let effective = |direction: uv::Vec3, b_body: &Option<&mut RigidBody>| {
+        let arm = point - a_body.center_of_mass(a_transform);
+        let mut inverse = a_body.inverse_mass()
+            + (a_body.inverse_inertia(a_transform) * arm.cross(direction))
+                .cross(arm)
+                .dot(direction);
+        if let Some(body) = b_body {
+            let arm = point - body.center_of_mass(b_transform);
+            inverse += body.inverse_mass()
+                + (body.inverse_inertia(b_transform) * arm.cross(direction))
+                    .cross(arm)
+                    .dot(direction)
+        }
+        inverse
+    };
+
+    let normal_speed = relative.dot(normal);
+    if normal_speed < 0.0 {
+        // Below a walking pace, bounces just turn into jitter.
+        let restitution = if normal_speed > -1.0 {
+            0.0
+        } else {
+            restitution
+        };
+        let normal_impulse = -(1.0 + restitution) * normal_speed / effective(normal, &b_body);
+
+        let tangent_velocity = relative - normal * normal_speed;
+        let mut impulse = normal * normal_impulse;
+        if tangent_velocity.mag_sq() > 1e-8 {
+            let tangent = tangent_velocity.normalized();
+            let tangent_impulse = (tangent_velocity.mag() / effective(tangent, &b_body))
+                .min(friction * normal_impulse);
+            impulse -= tangent * tangent_impulse
+        }
+
+        a_body.apply_impulse(a_transform, impulse, point);
+        if let Some(body) = b_body.as_mut() {
+            body.apply_impulse(b_transform, -impulse, point)
+        }
+    }
+
+    // Split the correction by inverse mass, leaving a little overlap so resting contacts
+    // stay in contact.
+    const SLOP: f32 = 0.05;
+    const PERCENT: f32 = 0.8;
+    let correction = (depth - SLOP).max(0.0) * PERCENT;
+    let a_inverse = a_body.inverse_mass();
+    let b_inverse = b_body.as_ref().map_or(0.0, |body| body.inverse_mass());
+    let total = a_inverse + b_inverse;
+    a_transform.position += normal * (correction * a_inverse / total);
+    if b_body.is_some() {
+        b_transform.position -= normal * (correction * b_inverse / total)
+    }
+}
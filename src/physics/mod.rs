@@ -0,0 +1,85 @@
+pub use body::RigidBody;
+pub use player::Player;
+
+pub mod body;
+pub mod contact;
+mod player;
+
+use crate::scene::Scene;
+
+/// Simulation settings. Objects with a [`RigidBody`] move; the rest of the scene is static
+/// world they collide with.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Physics {
+    /// In voxels per second squared.
+    pub gravity: uv::Vec3,
+    /// Fraction of the approach speed kept after a bounce.
+    pub restitution: f32,
+    /// Coulomb friction coefficient.
+    pub friction: f32,
+    /// Fraction of angular velocity lost per second. Contacts are merged into a single point,
+    /// so objects resting on a face rock slightly, and this lets them settle.
+    pub angular_damping: f32,
+}
+
+impl Physics {
+    /// Longest step taken at once. Longer frames are split up, so fast objects don't tunnel
+    /// through thin walls.
+    const MAX_STEP: f32 = 1.0 / 120.0;
+
+    pub fn new() -> Self {
+        Self {
+            gravity: uv::Vec3::new(0.0, 0.0, -30.0),
+            restitution: 0.3,
+            friction: 0.5,
+            angular_damping: 0.5,
+        }
+    }
+
+    /// Advances every dynamic object by `dt` seconds.
+    pub fn step(&self, scene: &mut Scene, dt: f32) {
+        let steps = (dt / Self::MAX_STEP).ceil().max(1.0);
+        for _ in 0..steps as u32 {
+            self.substep(scene, dt / steps)
+        }
+    }
+
+    fn substep(&self, scene: &mut Scene, dt: f32) {
+        let objects = scene.objects_mut();
+        for scene_object in objects.iter_mut() {
+            if let Some(body) = &mut scene_object.body {
+                body.velocity += self.gravity * dt;
+                body.angular_velocity *= (1.0 - self.angular_damping * dt).max(0.0);
+                body.integrate(&mut scene_object.transform, dt)
+            }
+        }
+
+        for j in 1..objects.len() {
+            let (left, right) = objects.split_at_mut(j);
+            let second = &mut right[0];
+            for first in left.iter_mut() {
+                // Always test the moving object's voxels against the other one.
+                let (a, b) = match (first.body.is_some(), second.body.is_some()) {
+                    (false, false) => continue,
+                    (true, _) => (&mut *first, &mut *second),
+                    (false, true) => (&mut *second, &mut *first),
+                };
+                if let Some(contact) = contact::find(a, b) {
+                    contact::resolve(
+                        (&mut a.transform, a.body.as_mut().unwrap()),
+                        (&mut b.transform, b.body.as_mut()),
+                        contact,
+                        self.restitution,
+                        self.friction,
+                    )
+                }
+            }
+        }
+    }
+}
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
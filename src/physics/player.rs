@@ -0,0 +1,131 @@
+use crate::voxel::{Object, VoxelBox, VoxelCoord};
+
+/// Box-shaped walking body, colliding with the voxels of an object in its local space.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Player {
+    /// Center of the bottom face.
+    pub feet: uv::Vec3,
+    pub velocity: uv::Vec3,
+    on_ground: bool,
+}
+
+/// Voxel faces closer than this count as touching, so the player can stand exactly on a face
+/// without sinking into it, and slide along walls without catching on them.
+const EPSILON: f32 = 1e-3;
+
+impl Player {
+    pub const HALF_WIDTH: f32 = 0.3;
+    pub const HEIGHT: f32 = 1.8;
+    pub const EYE_HEIGHT: f32 = 1.6;
+    /// Tallest ledge walked up without jumping.
+    pub const STEP_HEIGHT: f32 = 1.0;
+    pub const GRAVITY: f32 = 30.0;
+    pub const JUMP_SPEED: f32 = 9.0;
+
+    pub fn new(feet: uv::Vec3) -> Self {
+        Self {
+            feet,
+            velocity: uv::Vec3::zero(),
+            on_ground: false,
+        }
+    }
+
+    pub fn eye(&self) -> uv::Vec3 {
+        self.feet + uv::Vec3::new(0.0, 0.0, Self::EYE_HEIGHT)
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.on_ground
+    }
+
+    /// Moves for `dt` seconds with horizontal velocity `walk`, falling under gravity and
+    /// jumping if `jump` is set while standing on something.
+    pub fn update(&mut self, world: &Object, walk: uv::Vec2, jump: bool, dt: f32) {
+        self.velocity.x = walk.x;
+        self.velocity.y = walk.y;
+        self.velocity.z -= Self::GRAVITY * dt;
+        if jump && self.on_ground {
+            self.velocity.z = Self::JUMP_SPEED
+        }
+
+        // Resolve one axis at a time, vertical first so walking off a ledge uses the ground
+        // state from this frame.
+        let fall = self.velocity.z * dt;
+        let moved = self.sweep(world, 2, fall);
+        self.on_ground = fall < 0.0 && moved > fall;
+        if moved != fall {
+            self.velocity.z = 0.0
+        }
+        for axis in 0..2 {
+            let delta = self.velocity[axis] * dt;
+            let moved = self.sweep(world, axis, delta);
+            if moved != delta && !(self.on_ground && self.step_up(world, axis, delta, moved)) {
+                self.velocity[axis] = 0.0
+            }
+        }
+    }
+
+    fn bounds(&self) -> (uv::Vec3, uv::Vec3) {
+        (
+            self.feet - uv::Vec3::new(Self::HALF_WIDTH, Self::HALF_WIDTH, 0.0),
+            self.feet + uv::Vec3::new(Self::HALF_WIDTH, Self::HALF_WIDTH, Self::HEIGHT),
+        )
+    }
+
+    /// Moves up to `delta` along `axis`, stopping at the first solid voxel. Returns how far it
+    /// actually moved.
+    fn sweep(&mut self, world: &Object, axis: usize, delta: f32) -> f32 {
+        let (min, max) = self.bounds();
+        let mut moved = delta;
+        if delta > 0.0 {
+            let first = layer(max[axis], -EPSILON) + 1;
+            for layer in first..=layer(max[axis] + delta + EPSILON, -EPSILON) {
+                if blocked(world, min, max, axis, layer) {
+                    moved = (layer as f32 - 0.5 - max[axis]).max(0.0);
+                    break;
+                }
+            }
+        } else if delta < 0.0 {
+            let first = layer(min[axis], EPSILON) - 1;
+            for layer in (layer(min[axis] + delta - EPSILON, EPSILON)..=first).rev() {
+                if blocked(world, min, max, axis, layer) {
+                    moved = (layer as f32 + 0.5 - min[axis]).min(0.0);
+                    break;
+                }
+            }
+        }
+        self.feet[axis] += moved;
+        moved
+    }
+
+    /// Retries a horizontal move that hit something `moved` into it, lifted by up to one step.
+    /// Keeps the lifted attempt, settled back onto the ground, if it got further.
+    fn step_up(&mut self, world: &Object, axis: usize, delta: f32, moved: f32) -> bool {
+        let start = *self;
+        let lift = self.sweep(world, 2, Self::STEP_HEIGHT + EPSILON);
+        let stepped = self.sweep(world, axis, delta - moved);
+        if stepped.abs() <= EPSILON {
+            *self = start;
+            return false;
+        }
+        self.sweep(world, 2, -lift);
+        true
+    }
+}
+
+/// Voxel layer containing `pos` along one axis, nudged by `bias` so faces exactly on a voxel
+/// boundary land on the expected side.
+fn layer(pos: f32, bias: f32) -> i32 {
+    (pos + 0.5 + bias).floor() as i32
+}
+
+/// Whether any voxel in `layer` along `axis`, within the box's cross-section, is solid.
+fn blocked(world: &Object, min: uv::Vec3, max: uv::Vec3, axis: usize, layer: i32) -> bool {
+    let mut low = VoxelCoord::containing(min + uv::Vec3::broadcast(EPSILON));
+    let mut high = VoxelCoord::containing(max - uv::Vec3::broadcast(EPSILON));
+    low.vec[axis] = layer;
+    high.vec[axis] = layer;
+    VoxelBox::new(low, high)
+        .iterate()
+        .any(|coord| !world[coord].is_void())
+}
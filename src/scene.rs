@@ -0,0 +1,190 @@
+use crate::physics::RigidBody;
+use crate::voxel::{Brush, BrushMode, BrushShape, Object, RaycastHit, Voxel};
+
+/// Placement of an object in the scene. Object-local voxel coordinates are scaled, then
+/// rotated, then translated.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Transform {
+    pub position: uv::Vec3,
+    pub rotation: uv::Rotor3,
+    pub scale: f32,
+}
+
+pub struct SceneObject {
+    pub object: Object,
+    pub transform: Transform,
+    /// `None` for static objects, which never move.
+    pub body: Option<RigidBody>,
+}
+
+pub struct Scene {
+    objects: Vec<SceneObject>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SceneHit {
+    pub object: usize,
+    /// Hit in the object's local space, with `distance` converted to world units.
+    pub hit: RaycastHit,
+}
+
+impl Transform {
+    pub fn new(position: uv::Vec3, rotation: uv::Rotor3, scale: f32) -> Self {
+        Self {
+            position,
+            rotation,
+            scale,
+        }
+    }
+
+    pub fn identity() -> Self {
+        Self::new(uv::Vec3::zero(), uv::Rotor3::identity(), 1.0)
+    }
+
+    pub fn mat(&self) -> uv::Mat4 {
+        uv::Mat4::from_translation(self.position)
+            * self.rotation.into_matrix().into_homogeneous()
+            * uv::Mat4::from_scale(self.scale)
+    }
+
+    pub fn world_point(&self, point: uv::Vec3) -> uv::Vec3 {
+        self.rotation * (point * self.scale) + self.position
+    }
+
+    pub fn local_point(&self, point: uv::Vec3) -> uv::Vec3 {
+        self.rotation.reversed() * (point - self.position) / self.scale
+    }
+
+    /// Rotates a world direction into local space. Lengths are not scaled.
+    pub fn local_dir(&self, direction: uv::Vec3) -> uv::Vec3 {
+        self.rotation.reversed() * direction
+    }
+
+    pub fn world_dir(&self, direction: uv::Vec3) -> uv::Vec3 {
+        self.rotation * direction
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl SceneObject {
+    /// Recomputes the mass properties of a dynamic object after its voxels changed. An
+    /// object that lost all of its voxels becomes static.
+    pub fn update_body(&mut self) {
+        if let Some(body) = &mut self.body {
+            if !body.update(&self.object, 1.0, self.transform.scale) {
+                self.body = None
+            }
+        }
+    }
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+        }
+    }
+
+    pub fn new_test() -> Self {
+        let mut scene = Self::new();
+        scene.add(Object::new_test(), Transform::identity());
+        scene.add(
+            Object::new_test(),
+            Transform::new(
+                uv::Vec3::new(0.0, -120.0, 0.0),
+                uv::Rotor3::from_rotation_xy(std::f32::consts::FRAC_PI_4),
+                0.5,
+            ),
+        );
+        let mut ball = Object::new();
+        Brush::new(
+            BrushShape::Sphere {
+                center: uv::Vec3::zero(),
+                radius: 6.0,
+            },
+            BrushMode::Add,
+            Voxel::from_id(2),
+        )
+        .apply(&mut ball);
+        scene.add_dynamic(
+            ball,
+            Transform::new(
+                uv::Vec3::new(16.0, 16.0, 120.0),
+                uv::Rotor3::identity(),
+                1.0,
+            ),
+        );
+        scene
+    }
+
+    /// Adds a static object, returning its index.
+    pub fn add(&mut self, object: Object, transform: Transform) -> usize {
+        self.objects.push(SceneObject {
+            object,
+            transform,
+            body: None,
+        });
+        self.objects.len() - 1
+    }
+
+    /// Adds an object that physics moves around, returning its index. Empty objects have no
+    /// mass, so they are added as static.
+    pub fn add_dynamic(&mut self, object: Object, transform: Transform) -> usize {
+        let body = RigidBody::new(&object, 1.0, transform.scale);
+        self.objects.push(SceneObject {
+            object,
+            transform,
+            body,
+        });
+        self.objects.len() - 1
+    }
+
+    pub fn objects(&self) -> &[SceneObject] {
+        &self.objects
+    }
+
+    pub fn objects_mut(&mut self) -> &mut [SceneObject] {
+        &mut self.objects
+    }
+
+    pub fn object(&self, index: usize) -> &SceneObject {
+        &self.objects[index]
+    }
+
+    pub fn object_mut(&mut self, index: usize) -> &mut SceneObject {
+        &mut self.objects[index]
+    }
+
+    /// Closest voxel hit along a world-space ray, across all objects.
+    pub fn raycast(
+        &self,
+        origin: uv::Vec3,
+        direction: uv::Vec3,
+        max_distance: f32,
+    ) -> Option<SceneHit> {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter_map(|(index, scene_object)| {
+                let transform = scene_object.transform;
+                let mut hit = scene_object.object.raycast(
+                    transform.local_point(origin),
+                    transform.local_dir(direction),
+                    max_distance / transform.scale,
+                )?;
+                hit.distance *= transform.scale;
+                Some(SceneHit { object: index, hit })
+            })
+            .min_by(|a, b| {
+                a.hit
+                    .distance
+                    .partial_cmp(&b.hit.distance)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+}
@@ -0,0 +1,288 @@
+//! Compiles the GLSL in `assets/shaders` to SPIR-V. Used by `build.rs` to embed shaders and by
+//! hot reloading, so both resolve includes and variants the same way.
+//!
+//! Shaders can `#include` files relative to themselves or to `assets/shaders`. Files without a
+//! shader extension, like `.glsl`, are only compiled as includes. A shader can also declare
+//! variants, compiled again with extra preprocessor definitions, with lines like
+//!
+//! ```glsl
+//! // variant ao: AO
+//! // variant fancy: AO SHADOW_SAMPLES=4
+//! ```
+//!
+//! which turn `voxel.vert` into `voxel.ao.vert` and `voxel.fancy.vert` alongside it.
+
+use std::path::{Path, PathBuf};
+
+/// Where shaders are read from, relative to the working directory.
+pub const SHADER_DIR: &str = "assets/shaders";
+
+/// How deep includes can nest, to catch files including each other.
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// SPIR-V of one shader or variant.
+pub struct CompiledShader {
+    /// File name, with the variant name before the extension for variants.
+    pub name: String,
+    pub spirv: Vec<u8>,
+    /// Compiler warnings, empty if there were none.
+    pub warnings: String,
+}
+
+/// Shader stage of a file by its extension, or `None` if it isn't a shader.
+pub fn shader_kind(path: &Path) -> Option<shaderc::ShaderKind> {
+    match path.extension()?.to_str()? {
+        "vert" => Some(shaderc::ShaderKind::Vertex),
+        "frag" => Some(shaderc::ShaderKind::Fragment),
+        "comp" => Some(shaderc::ShaderKind::Compute),
+        _ => None,
+    }
+}
+
+/// Shaders in [`SHADER_DIR`], sorted by name.
+pub fn shader_paths() -> std::io::Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(SHADER_DIR)? {
+        let path = entry?.path();
+        if shader_kind(&path).is_some() {
+            paths.push(path)
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Compiles the shader at `path` and each of its variants. The error says which file, variant
+/// and line failed.
+pub fn compile(
+    compiler: &mut shaderc::Compiler,
+    path: &Path,
+) -> Result<Vec<CompiledShader>, String> {
+    let kind = shader_kind(path).ok_or_else(|| format!("{} is not a shader", path.display()))?;
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {}", path.display(), err))?;
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    // Errors name the shader by this, so they point at the file from the working directory.
+    let input_name = path.to_string_lossy();
+
+    let mut shaders = Vec::new();
+    for variant in std::iter::once(Ok(Variant::default())).chain(variants(path, &source)) {
+        let variant = variant?;
+        let mut options = shaderc::CompileOptions::new()
+            .ok_or_else(|| "failed to create shader compile options".to_owned())?;
+        options.set_include_callback(resolve_include);
+        for (name, value) in &variant.defines {
+            options.add_macro_definition(name, value.as_deref())
+        }
+        let artifact = compiler
+            .compile_into_spirv(&source, kind, &input_name, "main", Some(&options))
+            .map_err(|err| match &variant.name {
+                Some(name) => format!("{} (variant {}): {}", path.display(), name, err),
+                None => format!("{}: {}", path.display(), err),
+            })?;
+        shaders.push(CompiledShader {
+            name: variant.file_name(&file_name),
+            spirv: artifact.as_binary_u8().to_vec(),
+            warnings: if artifact.get_num_warnings() > 0 {
+                artifact.get_warning_messages()
+            } else {
+                String::new()
+            },
+        })
+    }
+    Ok(shaders)
+}
+
+/// Extra preprocessor definitions a shader is also compiled with.
+#[derive(Debug, Default, PartialEq)]
+struct Variant {
+    /// `None` for the shader as written.
+    name: Option<String>,
+    defines: Vec<(String, Option<String>)>,
+}
+
+impl Variant {
+    fn file_name(&self, shader: &str) -> String {
+        match (&self.name, shader.rsplit_once('.')) {
+            (Some(name), Some((stem, extension))) => format!("{}.{}.{}", stem, name, extension),
+            (Some(name), None) => format!("{}.{}", shader, name),
+            (None, _) => shader.to_owned(),
+        }
+    }
+}
+
+/// Parses the `// variant name: DEFINE=value ...` lines of a shader.
+fn variants<'a>(
+    path: &'a Path,
+    source: &'a str,
+) -> impl Iterator<Item = Result<Variant, String>> + 'a {
+    source.lines().enumerate().filter_map(move |(index, line)| {
+        let declaration = line.trim().strip_prefix("// variant ")?;
+        let error = |message: &str| format!("{}:{}: {}", path.display(), index + 1, message);
+        let (name, defines) = match declaration.split_once(':') {
+            Some((name, defines)) if !name.trim().is_empty() => (name.trim(), defines),
+            _ => return Some(Err(error("expected `// variant name: DEFINE ...`"))),
+        };
+        if name.contains(|c: char| !c.is_ascii_alphanumeric() && c != '_') {
+            return Some(Err(error(
+                "variant names can only have letters, digits and _",
+            )));
+        }
+        let defines = defines
+            .split_whitespace()
+            .map(|define| match define.split_once('=') {
+                Some((name, value)) => (name.to_owned(), Some(value.to_owned())),
+                None => (define.to_owned(), None),
+            })
+            .collect();
+        Some(Ok(Variant {
+            name: Some(name.to_owned()),
+            defines,
+        }))
+    })
+}
+
+/// Finds `#include "file"` next to the including file, then in [`SHADER_DIR`].
+/// `#include <file>` only looks in [`SHADER_DIR`].
+fn resolve_include(
+    requested: &str,
+    include_type: shaderc::IncludeType,
+    requesting: &str,
+    depth: usize,
+) -> shaderc::IncludeCallbackResult {
+    if depth > MAX_INCLUDE_DEPTH {
+        return Err(format!(
+            "includes nested more than {} deep, do files include each other?",
+            MAX_INCLUDE_DEPTH
+        ));
+    }
+    let root = Path::new(SHADER_DIR);
+    let mut candidates = Vec::new();
+    if include_type == shaderc::IncludeType::Relative {
+        if let Some(dir) = Path::new(requesting).parent() {
+            candidates.push(dir.join(requested))
+        }
+    }
+    candidates.push(root.join(requested));
+    candidates
+        .into_iter()
+        .find_map(|path| {
+            let content = std::fs::read_to_string(&path).ok()?;
+            Some(shaderc::ResolvedInclude {
+                resolved_name: path.to_string_lossy().into_owned(),
+                content,
+            })
+        })
+        .ok_or_else(|| format!("can't find {} to include", requested))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Vec<Variant>, String> {
+        variants(Path::new("assets/shaders/voxel.vert"), source).collect()
+    }
+
+    fn variant(name: &str, defines: &[(&str, Option<&str>)]) -> Variant {
+        Variant {
+            name: Some(name.to_owned()),
+            defines: defines
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.map(str::to_owned)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn variant_without_defines() {
+        assert_eq!(
+            parse("// variant plain:\n"),
+            Ok(vec![variant("plain", &[])])
+        );
+    }
+
+    #[test]
+    fn variant_with_defines() {
+        let source = "#version 450\n  // variant fancy: A B=2\n// variant ao: AO\nvoid main() {}\n";
+        assert_eq!(
+            parse(source),
+            Ok(vec![
+                variant("fancy", &[("A", None), ("B", Some("2"))]),
+                variant("ao", &[("AO", None)]),
+            ])
+        );
+        assert_eq!(
+            parse("// variants are declared like this\n"),
+            Ok(Vec::new())
+        );
+    }
+
+    #[test]
+    fn bad_variants_name_the_line() {
+        let missing_colon = parse("#version 450\n// variant ao AO\n").unwrap_err();
+        assert!(
+            missing_colon.starts_with("assets/shaders/voxel.vert:2: "),
+            "{}",
+            missing_colon
+        );
+        let bad_name = parse("\n\n// variant a.o: AO\n").unwrap_err();
+        assert!(
+            bad_name.starts_with("assets/shaders/voxel.vert:3: "),
+            "{}",
+            bad_name
+        );
+        assert!(parse("// variant : AO\n").is_err());
+    }
+
+    #[test]
+    fn variant_file_names() {
+        let ao = variant("ao", &[]);
+        assert_eq!(ao.file_name("voxel.vert"), "voxel.ao.vert");
+        assert_eq!(ao.file_name("voxel"), "voxel.ao");
+        assert_eq!(Variant::default().file_name("voxel.vert"), "voxel.vert");
+    }
+
+    #[test]
+    fn includes_resolve_next_to_the_shader_first() {
+        let dir = std::env::temp_dir().join(format!("stannox-include-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("voxel.vert"), "// next to the shader").unwrap();
+        std::fs::write(dir.join("local.glsl"), "// only next to the shader").unwrap();
+        let requesting = dir.join("main.vert");
+        let requesting = requesting.to_str().unwrap();
+        let include = |requested, include_type| {
+            resolve_include(requested, include_type, requesting, 1).map(|include| include.content)
+        };
+        let relative = include("voxel.vert", shaderc::IncludeType::Relative);
+        let standard = include("voxel.vert", shaderc::IncludeType::Standard);
+        let local = include("local.glsl", shaderc::IncludeType::Relative);
+        let missing = include("local.glsl", shaderc::IncludeType::Standard);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(relative.unwrap(), "// next to the shader");
+        assert_eq!(
+            standard.unwrap(),
+            std::fs::read_to_string(Path::new(SHADER_DIR).join("voxel.vert")).unwrap()
+        );
+        assert_eq!(local.unwrap(), "// only next to the shader");
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn includes_nest_at_most_max_depth() {
+        let include = |depth| {
+            resolve_include(
+                "voxel.vert",
+                shaderc::IncludeType::Standard,
+                "assets/shaders/voxel.frag",
+                depth,
+            )
+        };
+        assert!(include(MAX_INCLUDE_DEPTH).is_ok());
+        assert!(include(MAX_INCLUDE_DEPTH + 1).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+use erupt::vk;
+
+/// Something that went wrong setting up or talking to the GPU. Most variants say what was
+/// being done when it happened.
+#[derive(Debug)]
+pub enum Error {
+    /// The Vulkan library couldn't be loaded, usually because no driver is installed.
+    Loader(String),
+    /// No GPU can draw to the window.
+    NoSuitableDevice,
+    /// No GPU matches the one asked for.
+    GpuNotFound(String),
+    /// The GPU asked for can't draw to the window.
+    GpuUnsuitable(String),
+    /// The GPU was reset or its driver crashed. Nothing made with the device can be used
+    /// again, so the whole instance has to be recreated.
+    DeviceLost { context: &'static str },
+    /// Host or device memory ran out.
+    OutOfMemory { context: &'static str },
+    /// Device memory couldn't be allocated for some other reason.
+    Allocation {
+        context: &'static str,
+        error: vk_alloc::AllocatorError,
+    },
+    /// Shader bytes embedded at build time aren't valid SPIR-V.
+    InvalidShader(&'static str),
+    /// Any other failed Vulkan call.
+    Vulkan {
+        context: &'static str,
+        result: vk::Result,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// Error for a failed Vulkan call, giving device loss and running out of memory their own
+    /// variants.
+    pub(super) fn from_vk(context: &'static str, result: vk::Result) -> Self {
+        match result {
+            vk::Result::ERROR_DEVICE_LOST => Self::DeviceLost { context },
+            vk::Result::ERROR_OUT_OF_HOST_MEMORY | vk::Result::ERROR_OUT_OF_DEVICE_MEMORY => {
+                Self::OutOfMemory { context }
+            }
+            result => Self::Vulkan { context, result },
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Loader(message) => write!(f, "failed to load Vulkan: {}", message),
+            Self::NoSuitableDevice => write!(f, "no GPU supports drawing to this window"),
+            Self::GpuNotFound(selector) => {
+                write!(f, "no GPU matches {}, see `stannox gpus`", selector)
+            }
+            Self::GpuUnsuitable(name) => write!(f, "GPU {} can't draw to this window", name),
+            Self::DeviceLost { context } => write!(f, "GPU device lost while {}", context),
+            Self::OutOfMemory { context } => write!(f, "ran out of memory while {}", context),
+            Self::Allocation { context, error } => {
+                write!(
+                    f,
+                    "failed to allocate GPU memory while {}: {}",
+                    context, error
+                )
+            }
+            Self::InvalidShader(name) => write!(f, "shader {} is not valid SPIR-V", name),
+            Self::Vulkan { context, result } => write!(f, "failed while {}: {}", context, result),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Turns the error types of the Vulkan crates into [`Error`]s, saying what was being done.
+pub(super) trait Context<T> {
+    fn context(self, context: &'static str) -> Result<T>;
+}
+
+impl<T> Context<T> for erupt::utils::VulkanResult<T> {
+    fn context(self, context: &'static str) -> Result<T> {
+        self.result()
+            .map_err(|result| Error::from_vk(context, result))
+    }
+}
+
+impl<T> Context<T> for std::result::Result<T, erupt::LoaderError> {
+    fn context(self, context: &'static str) -> Result<T> {
+        self.map_err(|error| match error {
+            erupt::LoaderError::VulkanError(result) => Error::from_vk(context, result),
+            erupt::LoaderError::SymbolNotAvailable => {
+                Error::Loader(format!("missing function while {}", context))
+            }
+        })
+    }
+}
+
+impl<T> Context<T> for std::result::Result<T, vk_alloc::AllocatorError> {
+    fn context(self, context: &'static str) -> Result<T> {
+        self.map_err(|error| match error {
+            vk_alloc::AllocatorError::OutOfMemory => Error::OutOfMemory { context },
+            error => Error::Allocation { context, error },
+        })
+    }
+}
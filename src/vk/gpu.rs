@@ -0,0 +1,219 @@
+use super::error::Context;
+use super::{Error, Result};
+use erupt::vk;
+
+/// Picks a GPU instead of letting the instance choose, e.g. from `--gpu`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum GpuSelector {
+    /// Position in the list printed by `stannox gpus`.
+    Index(usize),
+    /// Case-insensitive part of the device name.
+    Name(String),
+}
+
+impl GpuSelector {
+    fn matches(&self, index: usize, name: &str) -> bool {
+        match self {
+            Self::Index(selected) => *selected == index,
+            Self::Name(part) => name.to_lowercase().contains(&part.to_lowercase()),
+        }
+    }
+}
+
+impl std::str::FromStr for GpuSelector {
+    type Err = String;
+
+    fn from_str(text: &str) -> std::result::Result<Self, Self::Err> {
+        if text.is_empty() {
+            return Err("expected a GPU index or part of its name".to_owned());
+        }
+        Ok(match text.parse() {
+            Ok(index) => Self::Index(index),
+            Err(_) => Self::Name(text.to_owned()),
+        })
+    }
+}
+
+impl std::fmt::Display for GpuSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "#{}", index),
+            Self::Name(part) => write!(f, "{:?}", part),
+        }
+    }
+}
+
+/// What a GPU is and what it can do, as printed by `stannox gpus`.
+#[derive(Debug, Clone)]
+pub struct GpuInfo {
+    pub index: usize,
+    pub name: String,
+    pub device_type: vk::PhysicalDeviceType,
+    pub api_version: u32,
+    /// Driver version in the vendor's own encoding.
+    pub driver_version: u32,
+    pub vendor_id: u32,
+    pub device_id: u32,
+    pub limits: vk::PhysicalDeviceLimits,
+    /// Size in bytes of each memory heap, and whether it's on the device.
+    pub memory_heaps: Vec<(vk::DeviceSize, bool)>,
+    pub queue_families: Vec<vk::QueueFamilyProperties>,
+}
+
+impl GpuInfo {
+    /// Describes every GPU, in the order [`GpuSelector::Index`] counts them.
+    pub fn list() -> Result<Vec<Self>> {
+        let created = super::instance::create_entry_instance_messenger(&[], false)?;
+        let instance = &created.instance;
+        let gpus = unsafe { instance.enumerate_physical_devices(None) }
+            .context("listing physical devices")
+            .map(|physical_devices| {
+                physical_devices
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, physical_device)| Self::query(instance, index, physical_device))
+                    .collect()
+            });
+        let super::instance::EntryInstanceMessenger {
+            mut entry,
+            mut instance,
+            ..
+        } = created;
+        unsafe {
+            instance.destroy_instance(None);
+            std::mem::ManuallyDrop::drop(&mut instance);
+            std::mem::ManuallyDrop::drop(&mut entry)
+        }
+        gpus
+    }
+
+    pub(super) fn query(
+        instance: &erupt::InstanceLoader,
+        index: usize,
+        physical_device: vk::PhysicalDevice,
+    ) -> Self {
+        let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        let memory = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let queue_families =
+            unsafe { instance.get_physical_device_queue_family_properties(physical_device, None) };
+        Self {
+            index,
+            name: unsafe { std::ffi::CStr::from_ptr(properties.device_name.as_ptr()) }
+                .to_string_lossy()
+                .into_owned(),
+            device_type: properties.device_type,
+            api_version: properties.api_version,
+            driver_version: properties.driver_version,
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            limits: properties.limits,
+            memory_heaps: memory.memory_heaps[..memory.memory_heap_count as usize]
+                .iter()
+                .map(|heap| {
+                    (
+                        heap.size,
+                        heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL),
+                    )
+                })
+                .collect(),
+            queue_families,
+        }
+    }
+
+    /// Order GPUs are preferred in when none is selected, lowest first.
+    pub(super) fn preference(&self) -> u32 {
+        match self.device_type {
+            vk::PhysicalDeviceType::DISCRETE_GPU => 0,
+            vk::PhysicalDeviceType::INTEGRATED_GPU => 1,
+            vk::PhysicalDeviceType::VIRTUAL_GPU => 2,
+            vk::PhysicalDeviceType::CPU => 3,
+            _ => 4,
+        }
+    }
+}
+
+impl std::fmt::Display for GpuInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "#{}: {} ({:?})", self.index, self.name, self.device_type)?;
+        writeln!(
+            f,
+            "  api {}.{}.{}, driver {:#x}, vendor {:#06x}, device {:#06x}",
+            vk::api_version_major(self.api_version),
+            vk::api_version_minor(self.api_version),
+            vk::api_version_patch(self.api_version),
+            self.driver_version,
+            self.vendor_id,
+            self.device_id
+        )?;
+        for (size, device_local) in &self.memory_heaps {
+            writeln!(
+                f,
+                "  memory heap: {} MiB{}",
+                size / (1024 * 1024),
+                if *device_local { ", device local" } else { "" }
+            )?
+        }
+        for (index, family) in self.queue_families.iter().enumerate() {
+            writeln!(
+                f,
+                "  queue family {}: {} x {:?}",
+                index, family.queue_count, family.queue_flags
+            )?
+        }
+        let limits = &self.limits;
+        writeln!(
+            f,
+            "  max image size: {}, max push constants: {} bytes, max allocations: {}",
+            limits.max_image_dimension2_d,
+            limits.max_push_constants_size,
+            limits.max_memory_allocation_count
+        )?;
+        writeln!(
+            f,
+            "  color samples: {:?}, depth samples: {:?}",
+            limits.framebuffer_color_sample_counts, limits.framebuffer_depth_sample_counts
+        )
+    }
+}
+
+/// Finds the GPU `selector` picks, or the most preferred one if there's no selector. `filter`
+/// returns what's needed from a suitable GPU, or `None` if it can't be used.
+pub(super) fn select<T>(
+    instance: &erupt::InstanceLoader,
+    selector: Option<&GpuSelector>,
+    filter: impl Fn(vk::PhysicalDevice) -> Option<T>,
+) -> Result<(vk::PhysicalDevice, T)> {
+    let physical_devices =
+        unsafe { instance.enumerate_physical_devices(None) }.context("listing physical devices")?;
+    let gpus = physical_devices
+        .into_iter()
+        .enumerate()
+        .map(|(index, physical_device)| {
+            (
+                physical_device,
+                GpuInfo::query(instance, index, physical_device),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let (physical_device, gpu, found) = match selector {
+        Some(selector) => {
+            let (physical_device, gpu) = gpus
+                .iter()
+                .find(|(_, gpu)| selector.matches(gpu.index, &gpu.name))
+                .ok_or_else(|| Error::GpuNotFound(selector.to_string()))?;
+            let found =
+                filter(*physical_device).ok_or_else(|| Error::GpuUnsuitable(gpu.name.clone()))?;
+            (*physical_device, gpu, found)
+        }
+        None => gpus
+            .iter()
+            .filter_map(|(physical_device, gpu)| {
+                filter(*physical_device).map(|found| (*physical_device, gpu, found))
+            })
+            .min_by_key(|(_, gpu, _)| gpu.preference())
+            .ok_or(Error::NoSuitableDevice)?,
+    };
+    log::info!("using GPU #{}: {}", gpu.index, gpu.name);
+    Ok((physical_device, found))
+}
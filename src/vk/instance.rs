@@ -1,17 +1,31 @@
-use super::{debug, QueueInfo, SwapchainInfo};
+use super::error::Context;
+use super::{debug, Error, QueueInfo, Result, SwapchainInfo};
 use erupt::{vk, ExtendableFromConst};
 use raw_window_handle::HasRawWindowHandle;
 
 const VAL_LAYER: *const std::os::raw::c_char = erupt::cstr!("VK_LAYER_KHRONOS_validation");
 
+/// How to set up an instance.
+#[derive(Debug, Clone, Default)]
+pub struct InstanceOptions {
+    /// Enables validation and logs Vulkan messages, if the layers for them are installed.
+    pub debug_mode: bool,
+    /// GPU to use instead of the most capable one.
+    pub gpu: Option<super::GpuSelector>,
+    /// File the pipeline cache is loaded from and saved to when the instance is dropped.
+    pub pipeline_cache: Option<std::path::PathBuf>,
+}
+
 pub trait Instance {
     fn instance(&self) -> &erupt::InstanceLoader;
     fn device(&self) -> &erupt::DeviceLoader;
     fn allocator(&self) -> &vk_alloc::Allocator;
     fn graphics_queue(&self) -> QueueInfo;
+    /// Cache shared by every pipeline created with this instance.
+    fn pipeline_cache(&self) -> vk::PipelineCache;
 
-    fn wait_idle(&self) {
-        unsafe { self.device().device_wait_idle().unwrap() }
+    fn wait_idle(&self) -> Result<()> {
+        unsafe { self.device().device_wait_idle() }.context("waiting for the GPU to finish")
     }
 }
 
@@ -24,6 +38,8 @@ pub struct HeadlessInstance {
     messenger: Option<vk::DebugUtilsMessengerEXT>,
     physical_device: vk::PhysicalDevice,
     graphics_queue: QueueInfo,
+    pipeline_cache: vk::PipelineCache,
+    pipeline_cache_path: Option<std::path::PathBuf>,
 }
 
 pub struct WindowedInstance {
@@ -37,24 +53,27 @@ pub struct WindowedInstance {
     physical_device: vk::PhysicalDevice,
     graphics_queue: QueueInfo,
     present_queue: QueueInfo,
+    pipeline_cache: vk::PipelineCache,
+    pipeline_cache_path: Option<std::path::PathBuf>,
 }
 
 impl HeadlessInstance {
-    pub fn new(debug_mode: bool) -> std::sync::Arc<Self> {
-        let mut instance_extensions = Vec::new();
+    pub fn new(options: InstanceOptions) -> Result<std::sync::Arc<Self>> {
         let device_extensions = vec![vk::KHR_SWAPCHAIN_EXTENSION_NAME];
-        let (instance_layers, device_layers) = if debug_mode {
-            instance_extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION_NAME);
-            (vec![VAL_LAYER], vec![VAL_LAYER])
+        let EntryInstanceMessenger {
+            entry,
+            instance,
+            messenger,
+            validation,
+        } = create_entry_instance_messenger(&[], options.debug_mode)?;
+        let device_layers = if validation {
+            vec![VAL_LAYER]
         } else {
-            (Vec::new(), Vec::new())
+            Vec::new()
         };
 
-        let (entry, instance, messenger) =
-            create_entry_instance_messenger(&instance_extensions, &instance_layers, debug_mode);
-
         let (physical_device, graphics_family) =
-            find_physical_device(&instance, |physical_device| {
+            super::gpu::select(&instance, options.gpu.as_ref(), |physical_device| {
                 let queue_families = unsafe {
                     instance.get_physical_device_queue_family_properties(physical_device, None)
                 };
@@ -66,8 +85,7 @@ impl HeadlessInstance {
                     None => return None,
                 };
                 Some(graphics_family)
-            })
-            .unwrap();
+            })?;
 
         let (device, [graphics_queue]) = create_device(
             &instance,
@@ -75,12 +93,18 @@ impl HeadlessInstance {
             &device_layers,
             physical_device,
             &[graphics_family],
-        );
+        )?;
 
-        let allocator =
-            vk_alloc::Allocator::new(&instance, physical_device, &Default::default()).unwrap();
+        let allocator = vk_alloc::Allocator::new(&instance, physical_device, &Default::default())
+            .context("creating the memory allocator")?;
+        let pipeline_cache = super::pipeline_cache::create(
+            &instance,
+            &device,
+            physical_device,
+            options.pipeline_cache.as_deref(),
+        )?;
 
-        std::sync::Arc::new(Self {
+        Ok(std::sync::Arc::new(Self {
             entry,
             instance,
             device,
@@ -89,7 +113,9 @@ impl HeadlessInstance {
             messenger,
             physical_device,
             graphics_queue,
-        })
+            pipeline_cache,
+            pipeline_cache_path: options.pipeline_cache,
+        }))
     }
 }
 
@@ -109,11 +135,26 @@ impl Instance for HeadlessInstance {
     fn graphics_queue(&self) -> QueueInfo {
         self.graphics_queue
     }
+
+    fn pipeline_cache(&self) -> vk::PipelineCache {
+        self.pipeline_cache
+    }
 }
 
 impl Drop for HeadlessInstance {
     fn drop(&mut self) {
+        if let Some(path) = &self.pipeline_cache_path {
+            super::pipeline_cache::save(
+                &self.instance,
+                &self.device,
+                self.physical_device,
+                self.pipeline_cache,
+                path,
+            )
+        }
         unsafe {
+            self.device
+                .destroy_pipeline_cache(Some(self.pipeline_cache), None);
             self.allocator.cleanup(&self.device);
             self.device.destroy_device(None);
             if let Some(messenger) = self.messenger {
@@ -129,28 +170,34 @@ impl Drop for HeadlessInstance {
 }
 
 impl WindowedInstance {
-    pub fn new(window: &impl HasRawWindowHandle, debug_mode: bool) -> std::sync::Arc<Self> {
-        let mut instance_extensions =
-            erupt::utils::surface::enumerate_required_extensions(window).unwrap();
+    pub fn new(
+        window: &impl HasRawWindowHandle,
+        options: InstanceOptions,
+    ) -> Result<std::sync::Arc<Self>> {
+        let instance_extensions = erupt::utils::surface::enumerate_required_extensions(window)
+            .context("finding the extensions the window needs")?;
         let device_extensions = vec![vk::KHR_SWAPCHAIN_EXTENSION_NAME];
-        let (instance_layers, device_layers) = if debug_mode {
-            instance_extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION_NAME);
-            (vec![VAL_LAYER], vec![VAL_LAYER])
+        let EntryInstanceMessenger {
+            entry,
+            instance,
+            messenger,
+            validation,
+        } = create_entry_instance_messenger(&instance_extensions, options.debug_mode)?;
+        let device_layers = if validation {
+            vec![VAL_LAYER]
         } else {
-            (Vec::new(), Vec::new())
+            Vec::new()
         };
 
-        let (entry, instance, messenger) =
-            create_entry_instance_messenger(&instance_extensions, &instance_layers, debug_mode);
-
-        let surface =
-            unsafe { erupt::utils::surface::create_surface(&instance, window, None) }.unwrap();
+        let surface = unsafe { erupt::utils::surface::create_surface(&instance, window, None) }
+            .context("creating the window surface")?;
 
         let (physical_device, (graphics_family, present_family)) =
-            find_physical_device(&instance, |physical_device| {
+            super::gpu::select(&instance, options.gpu.as_ref(), |physical_device| {
                 let queue_families = unsafe {
                     instance.get_physical_device_queue_family_properties(physical_device, None)
                 };
+                // A family whose support can't be queried counts as unsupported.
                 let present_family = match (0..queue_families.len()).find(|index| {
                     unsafe {
                         instance.get_physical_device_surface_support_khr(
@@ -159,7 +206,8 @@ impl WindowedInstance {
                             surface,
                         )
                     }
-                    .unwrap()
+                    .ok()
+                    .unwrap_or(false)
                 }) {
                     Some(index) => index as u32,
                     None => return None,
@@ -172,8 +220,7 @@ impl WindowedInstance {
                     None => return None,
                 };
                 Some((graphics_family, present_family))
-            })
-            .unwrap();
+            })?;
 
         let (device, [graphics_queue, present_queue]) = create_device(
             &instance,
@@ -181,12 +228,18 @@ impl WindowedInstance {
             &device_layers,
             physical_device,
             &[graphics_family, present_family],
-        );
+        )?;
 
-        let allocator =
-            vk_alloc::Allocator::new(&instance, physical_device, &Default::default()).unwrap();
+        let allocator = vk_alloc::Allocator::new(&instance, physical_device, &Default::default())
+            .context("creating the memory allocator")?;
+        let pipeline_cache = super::pipeline_cache::create(
+            &instance,
+            &device,
+            physical_device,
+            options.pipeline_cache.as_deref(),
+        )?;
 
-        std::sync::Arc::new(Self {
+        Ok(std::sync::Arc::new(Self {
             entry,
             instance,
             device,
@@ -197,19 +250,69 @@ impl WindowedInstance {
             physical_device,
             graphics_queue,
             present_queue,
-        })
+            pipeline_cache,
+            pipeline_cache_path: options.pipeline_cache,
+        }))
     }
 
     pub fn present_queue(&self) -> QueueInfo {
         self.present_queue
     }
 
-    pub fn swapchain_info(&self, (width, height): (u32, u32)) -> SwapchainInfo {
+    /// Sample counts both colour and depth attachments support.
+    pub(super) fn supported_samples(&self) -> vk::SampleCountFlags {
+        let properties = unsafe {
+            self.instance
+                .get_physical_device_properties(self.physical_device)
+        };
+        properties.limits.framebuffer_color_sample_counts
+            & properties.limits.framebuffer_depth_sample_counts
+    }
+
+    /// The first depth format usable as an attachment, preferring more precision.
+    pub(super) fn depth_format(&self) -> Result<vk::Format> {
+        const CANDIDATES: [vk::Format; 4] = [
+            vk::Format::D32_SFLOAT,
+            vk::Format::D32_SFLOAT_S8_UINT,
+            vk::Format::D24_UNORM_S8_UINT,
+            vk::Format::D16_UNORM,
+        ];
+        let format = CANDIDATES
+            .iter()
+            .copied()
+            .find(|&format| {
+                let properties = unsafe {
+                    self.instance
+                        .get_physical_device_format_properties(self.physical_device, format)
+                };
+                properties
+                    .optimal_tiling_features
+                    .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
+            })
+            .ok_or(Error::Vulkan {
+                context: "finding a depth format",
+                result: vk::Result::ERROR_FORMAT_NOT_SUPPORTED,
+            })?;
+        if format != CANDIDATES[0] {
+            log::info!(
+                "{:?} isn't supported, using {:?} for depth",
+                CANDIDATES[0],
+                format
+            )
+        }
+        Ok(format)
+    }
+
+    pub fn swapchain_info(
+        &self,
+        (width, height): (u32, u32),
+        present_mode: super::PresentMode,
+    ) -> Result<SwapchainInfo> {
         let surface_caps = unsafe {
             self.instance
                 .get_physical_device_surface_capabilities_khr(self.physical_device, self.surface)
         }
-        .unwrap();
+        .context("querying surface capabilities")?;
 
         let surface_formats = unsafe {
             self.instance.get_physical_device_surface_formats_khr(
@@ -218,8 +321,11 @@ impl WindowedInstance {
                 None,
             )
         }
-        .unwrap();
-        let first_surface_format = surface_formats[0];
+        .context("querying surface formats")?;
+        let first_surface_format = *surface_formats.first().ok_or(Error::Vulkan {
+            context: "querying surface formats",
+            result: vk::Result::ERROR_FORMAT_NOT_SUPPORTED,
+        })?;
         let surface_format = surface_formats
             .into_iter()
             .find(|surface_format| {
@@ -235,11 +341,18 @@ impl WindowedInstance {
                 None,
             )
         }
-        .unwrap();
-        let present_mode = present_modes
-            .into_iter()
-            .find(|present_mode| *present_mode == vk::PresentModeKHR::MAILBOX_KHR)
-            .unwrap_or(vk::PresentModeKHR::FIFO_KHR);
+        .context("querying present modes")?;
+        let present_mode = if present_modes.contains(&present_mode.vk()) {
+            present_mode.vk()
+        } else {
+            log::warn!(
+                "present mode {:?} is not supported, falling back to {:?}. Supported modes: {:?}",
+                present_mode,
+                super::PresentMode::Fifo,
+                present_modes
+            );
+            vk::PresentModeKHR::FIFO_KHR
+        };
 
         let extent = vk::Extent2D {
             width: width.clamp(
@@ -252,13 +365,13 @@ impl WindowedInstance {
             ),
         };
 
-        SwapchainInfo {
+        Ok(SwapchainInfo {
             surface: self.surface,
             surface_caps,
             surface_format,
             present_mode,
             extent,
-        }
+        })
     }
 }
 
@@ -278,11 +391,26 @@ impl Instance for WindowedInstance {
     fn graphics_queue(&self) -> QueueInfo {
         self.graphics_queue
     }
+
+    fn pipeline_cache(&self) -> vk::PipelineCache {
+        self.pipeline_cache
+    }
 }
 
 impl Drop for WindowedInstance {
     fn drop(&mut self) {
+        if let Some(path) = &self.pipeline_cache_path {
+            super::pipeline_cache::save(
+                &self.instance,
+                &self.device,
+                self.physical_device,
+                self.pipeline_cache,
+                path,
+            )
+        }
         unsafe {
+            self.device
+                .destroy_pipeline_cache(Some(self.pipeline_cache), None);
             self.allocator.cleanup(&self.device);
             self.device.destroy_device(None);
             self.instance.destroy_surface_khr(Some(self.surface), None);
@@ -298,67 +426,128 @@ impl Drop for WindowedInstance {
     }
 }
 
-fn create_entry_instance_messenger(
+pub(super) struct EntryInstanceMessenger {
+    pub(super) entry: std::mem::ManuallyDrop<erupt::EntryLoader>,
+    pub(super) instance: std::mem::ManuallyDrop<erupt::InstanceLoader>,
+    pub(super) messenger: Option<vk::DebugUtilsMessengerEXT>,
+    /// Whether the validation layer is enabled, so devices should enable it too.
+    pub(super) validation: bool,
+}
+
+/// Loads Vulkan and creates the instance with `instance_extensions`. In debug mode the
+/// validation layer and debug messenger are enabled too, if they're installed.
+pub(super) fn create_entry_instance_messenger(
     instance_extensions: &[*const std::os::raw::c_char],
-    instance_layers: &[*const std::os::raw::c_char],
     debug_mode: bool,
-) -> (
-    std::mem::ManuallyDrop<erupt::EntryLoader>,
-    std::mem::ManuallyDrop<erupt::InstanceLoader>,
-    Option<vk::DebugUtilsMessengerEXT>,
-) {
-    let entry = erupt::EntryLoader::new().unwrap();
+) -> Result<EntryInstanceMessenger> {
+    let entry = erupt::EntryLoader::new().map_err(|err| Error::Loader(err.to_string()))?;
+
+    let available_layers = unsafe { entry.enumerate_instance_layer_properties(None) }
+        .context("listing instance layers")?;
+    let validation = debug_mode
+        && available_layers
+            .iter()
+            .any(|layer| c_str_eq(&layer.layer_name, VAL_LAYER));
+    if debug_mode && !validation {
+        log::warn!("validation layer is not installed, continuing without validation");
+    }
+    let instance_layers = if validation {
+        vec![VAL_LAYER]
+    } else {
+        Vec::new()
+    };
+
+    // Layers can provide extensions of their own, debug utils in particular.
+    let mut available_extensions =
+        unsafe { entry.enumerate_instance_extension_properties(None, None) }
+            .context("listing instance extensions")?;
+    if validation {
+        let layer = unsafe { std::ffi::CStr::from_ptr(VAL_LAYER) };
+        available_extensions.extend(
+            unsafe { entry.enumerate_instance_extension_properties(Some(layer), None) }
+                .context("listing validation layer extensions")?,
+        );
+    }
+    let has_extension = |name| {
+        available_extensions
+            .iter()
+            .any(|extension| c_str_eq(&extension.extension_name, name))
+    };
+    let missing_extensions = instance_extensions
+        .iter()
+        .filter(|name| !has_extension(**name))
+        .map(|name| unsafe { std::ffi::CStr::from_ptr(*name) }.to_string_lossy())
+        .collect::<Vec<_>>();
+    if !missing_extensions.is_empty() {
+        log::error!(
+            "missing instance extensions: {}",
+            missing_extensions.join(", ")
+        );
+        return Err(Error::Vulkan {
+            context: "checking instance extensions",
+            result: vk::Result::ERROR_EXTENSION_NOT_PRESENT,
+        });
+    }
+    let debug_utils = debug_mode && has_extension(vk::EXT_DEBUG_UTILS_EXTENSION_NAME);
+    if debug_mode && !debug_utils {
+        log::warn!("debug utils extension is not available, Vulkan messages won't be logged");
+    }
+    let mut instance_extensions = instance_extensions.to_vec();
+    if debug_utils {
+        instance_extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION_NAME)
+    }
 
     let application_info =
         vk::ApplicationInfoBuilder::new().api_version(vk::make_api_version(0, 1, 2, 0));
-    if debug_mode {
+    if debug_utils {
         let messenger_create_info = debug::create_info();
         let instance_create_info = vk::InstanceCreateInfoBuilder::new()
             .extend_from(&messenger_create_info)
             .application_info(&application_info)
-            .enabled_layer_names(instance_layers)
-            .enabled_extension_names(instance_extensions);
-        let instance =
-            unsafe { erupt::InstanceLoader::new(&entry, &instance_create_info, None) }.unwrap();
+            .enabled_layer_names(&instance_layers)
+            .enabled_extension_names(&instance_extensions);
+        let instance = unsafe { erupt::InstanceLoader::new(&entry, &instance_create_info, None) }
+            .context("creating the instance")?;
         let messenger =
             unsafe { instance.create_debug_utils_messenger_ext(&messenger_create_info, None) }
-                .unwrap();
-        (
-            std::mem::ManuallyDrop::new(entry),
-            std::mem::ManuallyDrop::new(instance),
-            Some(messenger),
-        )
+                .context("creating the debug messenger")?;
+        Ok(EntryInstanceMessenger {
+            entry: std::mem::ManuallyDrop::new(entry),
+            instance: std::mem::ManuallyDrop::new(instance),
+            messenger: Some(messenger),
+            validation,
+        })
     } else {
         let instance_create_info = vk::InstanceCreateInfoBuilder::new()
             .application_info(&application_info)
-            .enabled_layer_names(instance_layers)
-            .enabled_extension_names(instance_extensions);
-        let instance =
-            unsafe { erupt::InstanceLoader::new(&entry, &instance_create_info, None) }.unwrap();
-        (
-            std::mem::ManuallyDrop::new(entry),
-            std::mem::ManuallyDrop::new(instance),
-            None,
-        )
+            .enabled_layer_names(&instance_layers)
+            .enabled_extension_names(&instance_extensions);
+        let instance = unsafe { erupt::InstanceLoader::new(&entry, &instance_create_info, None) }
+            .context("creating the instance")?;
+        Ok(EntryInstanceMessenger {
+            entry: std::mem::ManuallyDrop::new(entry),
+            instance: std::mem::ManuallyDrop::new(instance),
+            messenger: None,
+            validation,
+        })
     }
 }
 
-fn find_physical_device<T>(
-    instance: &erupt::InstanceLoader,
-    filter: impl Fn(vk::PhysicalDevice) -> Option<T>,
-) -> Option<(vk::PhysicalDevice, T)> {
-    let physical_devices = unsafe { instance.enumerate_physical_devices(None) }.unwrap();
-    physical_devices
-        .into_iter()
-        .filter_map(|physical_device| filter(physical_device).map(|x| (physical_device, x)))
-        .min_by_key(|(physical_device, _)| {
-            let properties = unsafe { instance.get_physical_device_properties(*physical_device) };
-            match properties.device_type {
-                vk::PhysicalDeviceType::DISCRETE_GPU => 0,
-                vk::PhysicalDeviceType::INTEGRATED_GPU => 1,
-                _ => 2,
-            }
-        })
+/// Aspects of a depth format that attachment views cover, including stencil if it has one.
+pub(super) fn depth_aspect(format: vk::Format) -> vk::ImageAspectFlags {
+    match format {
+        vk::Format::D16_UNORM_S8_UINT
+        | vk::Format::D24_UNORM_S8_UINT
+        | vk::Format::D32_SFLOAT_S8_UINT => {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        }
+        _ => vk::ImageAspectFlags::DEPTH,
+    }
+}
+
+/// Compares a fixed-size name from a Vulkan properties struct with a name constant.
+fn c_str_eq(name: &[std::os::raw::c_char], other: *const std::os::raw::c_char) -> bool {
+    unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) == std::ffi::CStr::from_ptr(other) }
 }
 
 fn create_device<const N: usize>(
@@ -367,7 +556,7 @@ fn create_device<const N: usize>(
     device_layers: &[*const std::os::raw::c_char],
     physical_device: vk::PhysicalDevice,
     queue_families: &[u32; N],
-) -> (std::mem::ManuallyDrop<erupt::DeviceLoader>, [QueueInfo; N]) {
+) -> Result<(std::mem::ManuallyDrop<erupt::DeviceLoader>, [QueueInfo; N])> {
     let unique_queues = queue_families
         .iter()
         .collect::<std::collections::HashSet<_>>();
@@ -387,7 +576,7 @@ fn create_device<const N: usize>(
         .enabled_features(&features);
     let device =
         unsafe { erupt::DeviceLoader::new(instance, physical_device, &device_create_info, None) }
-            .unwrap();
+            .context("creating the logical device")?;
     //TODO: simplify when array_map stabilizes
     let mut queues = [QueueInfo::default(); N];
     for i in 0..N {
@@ -396,5 +585,5 @@ fn create_device<const N: usize>(
             queue: unsafe { device.get_device_queue(queue_families[i], 0) },
         }
     }
-    (std::mem::ManuallyDrop::new(device), queues)
+    Ok((std::mem::ManuallyDrop::new(device), queues))
 }
@@ -1,5 +1,9 @@
-pub use instance::{HeadlessInstance, Instance, WindowedInstance};
-pub use renderable::{Renderable, Swapchain};
+pub use error::{Error, Result};
+pub use gpu::{GpuInfo, GpuSelector};
+pub use instance::{HeadlessInstance, Instance, InstanceOptions, WindowedInstance};
+pub use renderable::{PresentMode, Renderable, Swapchain, SwapchainOptions};
+pub use screenshot::Screenshot;
+pub use selection_box::SelectionBoxRenderer;
 use types::*;
 pub use voxel_mesh::{VoxelMeshManager, VoxelMeshRenderer};
 
@@ -9,10 +13,89 @@ macro_rules! include_shader {
     };
 }
 
+mod error;
+mod gpu;
 mod instance;
+mod pipeline_cache;
 mod renderable;
+mod screenshot;
+mod selection_box;
 mod voxel_mesh;
 
+/// A shader's file name and SPIR-V. The SPIR-V is embedded with [`include_shader!`] until hot
+/// reloading replaces it.
+#[derive(Debug, Clone)]
+struct Shader {
+    name: &'static str,
+    spirv: std::borrow::Cow<'static, [u8]>,
+}
+
+impl Shader {
+    fn embedded(name: &'static str, spirv: &'static [u8]) -> Self {
+        Self {
+            name,
+            spirv: std::borrow::Cow::Borrowed(spirv),
+        }
+    }
+
+    /// Copy with `spirv` instead if this is the shader called `name`.
+    fn replaced(&self, name: &str, spirv: &[u8]) -> Option<Self> {
+        (self.name == name).then(|| Self {
+            name: self.name,
+            spirv: std::borrow::Cow::Owned(spirv.to_vec()),
+        })
+    }
+
+    fn module(&self, device: &erupt::DeviceLoader) -> Result<erupt::vk::ShaderModule> {
+        use error::Context;
+        let code =
+            erupt::utils::decode_spv(&self.spirv).map_err(|_| Error::InvalidShader(self.name))?;
+        let create_info = erupt::vk::ShaderModuleCreateInfoBuilder::new().code(&code);
+        unsafe { device.create_shader_module(&create_info, None) }
+            .context("creating a shader module")
+    }
+}
+
+/// The vertex and fragment shader of a graphics pipeline.
+#[derive(Debug, Clone)]
+struct ShaderPair {
+    vert: Shader,
+    frag: Shader,
+}
+
+impl ShaderPair {
+    /// Copy with the shader called `name` replaced by `spirv`, or `None` if neither is called
+    /// that.
+    fn replaced(&self, name: &str, spirv: &[u8]) -> Option<Self> {
+        if let Some(vert) = self.vert.replaced(name, spirv) {
+            Some(Self {
+                vert,
+                frag: self.frag.clone(),
+            })
+        } else {
+            self.frag.replaced(name, spirv).map(|frag| Self {
+                vert: self.vert.clone(),
+                frag,
+            })
+        }
+    }
+
+    /// Creates both shader modules, which the caller destroys once the pipeline is made.
+    fn modules(
+        &self,
+        device: &erupt::DeviceLoader,
+    ) -> Result<(erupt::vk::ShaderModule, erupt::vk::ShaderModule)> {
+        let vert = self.vert.module(device)?;
+        match self.frag.module(device) {
+            Ok(frag) => Ok((vert, frag)),
+            Err(err) => {
+                unsafe { device.destroy_shader_module(Some(vert), None) };
+                Err(err)
+            }
+        }
+    }
+}
+
 mod debug {
     use erupt::vk;
 
@@ -86,5 +169,29 @@ mod types {
     pub struct RenderInfo {
         pub(super) render_pass: vk::RenderPass,
         pub(super) extent: vk::Extent2D,
+        pub(super) samples: vk::SampleCountFlagBits,
+        pub(super) reversed_z: bool,
+    }
+
+    impl RenderInfo {
+        /// Projection with an infinite far plane, matching the depth buffer's direction.
+        /// `v_fov` is in radians.
+        pub(super) fn perspective_mat(&self, v_fov: f32) -> uv::Mat4 {
+            let aspect_ratio = self.extent.width as f32 / self.extent.height as f32;
+            if self.reversed_z {
+                uv::projection::perspective_reversed_infinite_z_vk(v_fov, aspect_ratio, 0.1)
+            } else {
+                uv::projection::perspective_infinite_z_vk(v_fov, aspect_ratio, 0.1)
+            }
+        }
+
+        /// Depth test keeping the nearer fragment.
+        pub(super) fn depth_compare_op(&self) -> vk::CompareOp {
+            if self.reversed_z {
+                vk::CompareOp::GREATER
+            } else {
+                vk::CompareOp::LESS
+            }
+        }
     }
 }
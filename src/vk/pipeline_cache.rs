@@ -0,0 +1,198 @@
+use super::error::Context;
+use super::Result;
+use erupt::vk;
+use std::convert::TryInto;
+
+/// Written before the driver's cache data, so a cache from another GPU or driver version is
+/// never handed to the driver.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+struct CacheHeader {
+    vendor_id: u32,
+    device_id: u32,
+    driver_version: u32,
+    uuid: [u8; vk::UUID_SIZE as usize],
+}
+
+impl CacheHeader {
+    const MAGIC: &'static [u8; 8] = b"SNXPIPE\0";
+    const LEN: usize = Self::MAGIC.len() + 3 * 4 + vk::UUID_SIZE as usize;
+
+    fn new(instance: &erupt::InstanceLoader, physical_device: vk::PhysicalDevice) -> Self {
+        let properties = unsafe { instance.get_physical_device_properties(physical_device) };
+        Self {
+            vendor_id: properties.vendor_id,
+            device_id: properties.device_id,
+            driver_version: properties.driver_version,
+            uuid: properties.pipeline_cache_uuid,
+        }
+    }
+
+    fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(Self::MAGIC);
+        bytes.extend_from_slice(&self.vendor_id.to_le_bytes());
+        bytes.extend_from_slice(&self.device_id.to_le_bytes());
+        bytes.extend_from_slice(&self.driver_version.to_le_bytes());
+        bytes.extend_from_slice(&self.uuid)
+    }
+
+    /// Splits `bytes` into a header and the cache data after it, if it starts with one.
+    fn read(bytes: &[u8]) -> Option<(Self, &[u8])> {
+        if bytes.len() < Self::LEN || !bytes.starts_with(Self::MAGIC) {
+            return None;
+        }
+        let (header, data) = bytes.split_at(Self::LEN);
+        let word = |index: usize| {
+            let start = Self::MAGIC.len() + 4 * index;
+            u32::from_le_bytes(header[start..start + 4].try_into().unwrap())
+        };
+        let mut uuid = [0; vk::UUID_SIZE as usize];
+        uuid.copy_from_slice(&header[Self::MAGIC.len() + 12..]);
+        Some((
+            Self {
+                vendor_id: word(0),
+                device_id: word(1),
+                driver_version: word(2),
+                uuid,
+            },
+            data,
+        ))
+    }
+}
+
+/// Creates the pipeline cache, starting from the one saved at `path` if it was made by the
+/// same GPU and driver.
+pub(super) fn create(
+    instance: &erupt::InstanceLoader,
+    device: &erupt::DeviceLoader,
+    physical_device: vk::PhysicalDevice,
+    path: Option<&std::path::Path>,
+) -> Result<vk::PipelineCache> {
+    let saved = path.and_then(|path| match std::fs::read(path) {
+        Ok(bytes) => Some(bytes),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => {
+            log::warn!("failed to read {}: {}", path.display(), err);
+            None
+        }
+    });
+    let header = CacheHeader::new(instance, physical_device);
+    let initial_data = initial_data(saved.as_deref(), &header);
+
+    let create_info = vk::PipelineCacheCreateInfoBuilder::new()
+        .initial_data_size(initial_data.len())
+        .initial_data(initial_data.as_ptr().cast());
+    unsafe { device.create_pipeline_cache(&create_info, None) }
+        .context("creating the pipeline cache")
+}
+
+/// The driver's data from a `saved` cache, or nothing if there isn't one or it wasn't made with
+/// `header`.
+fn initial_data<'a>(saved: Option<&'a [u8]>, header: &CacheHeader) -> &'a [u8] {
+    match saved.map(CacheHeader::read) {
+        Some(Some((saved_header, data))) if saved_header == *header => data,
+        Some(_) => {
+            log::info!("pipeline cache is from another GPU or driver, starting over");
+            &[]
+        }
+        None => &[],
+    }
+}
+
+/// Writes the contents of `cache` to `path`. Failing only makes the next start slower, so
+/// errors are logged instead of returned.
+pub(super) fn save(
+    instance: &erupt::InstanceLoader,
+    device: &erupt::DeviceLoader,
+    physical_device: vk::PhysicalDevice,
+    cache: vk::PipelineCache,
+    path: &std::path::Path,
+) {
+    let mut size = 0;
+    let sized = unsafe { device.get_pipeline_cache_data(cache, &mut size, std::ptr::null_mut()) };
+    let mut data = vec![0u8; size];
+    let filled = sized.result().and_then(|()| {
+        unsafe { device.get_pipeline_cache_data(cache, &mut size, data.as_mut_ptr().cast()) }
+            .result()
+    });
+    if let Err(result) = filled {
+        return log::warn!("failed to get pipeline cache data: {}", result);
+    }
+    data.truncate(size);
+
+    let mut bytes = Vec::with_capacity(CacheHeader::LEN + data.len());
+    CacheHeader::new(instance, physical_device).write(&mut bytes);
+    bytes.extend_from_slice(&data);
+    // Written next to the cache and renamed over it, so a crash can't leave half a file.
+    let temp_path = path.with_extension("tmp");
+    let written = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|()| std::fs::write(&temp_path, &bytes))
+        .and_then(|()| std::fs::rename(&temp_path, path));
+    match written {
+        Ok(()) => log::debug!("saved {} bytes of pipeline cache", data.len()),
+        Err(err) => log::warn!("failed to save {}: {}", path.display(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> CacheHeader {
+        CacheHeader {
+            vendor_id: 0x10de,
+            device_id: 0x2484,
+            driver_version: 0x1234_5678,
+            uuid: [7; vk::UUID_SIZE as usize],
+        }
+    }
+
+    fn written(header: CacheHeader, data: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        header.write(&mut bytes);
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn header_round_trips() {
+        let data = [1, 2, 3, 4, 5];
+        let bytes = written(header(), &data);
+        assert_eq!(bytes.len(), CacheHeader::LEN + data.len());
+        assert_eq!(CacheHeader::read(&bytes), Some((header(), &data[..])));
+        let bytes = written(header(), &[]);
+        assert_eq!(CacheHeader::read(&bytes), Some((header(), &[][..])));
+    }
+
+    #[test]
+    fn short_or_foreign_data_has_no_header() {
+        let bytes = written(header(), &[1, 2, 3]);
+        assert_eq!(CacheHeader::read(&bytes[..CacheHeader::LEN - 1]), None);
+        assert_eq!(CacheHeader::read(&[]), None);
+        let mut wrong_magic = bytes;
+        wrong_magic[0] ^= 0xff;
+        assert_eq!(CacheHeader::read(&wrong_magic), None);
+    }
+
+    #[test]
+    fn only_a_matching_cache_is_used() {
+        let data = [1, 2, 3];
+        let bytes = written(header(), &data);
+        assert_eq!(initial_data(Some(&bytes), &header()), data);
+
+        let mut other_uuid = header();
+        other_uuid.uuid[15] = 8;
+        let mut other_driver = header();
+        other_driver.driver_version += 1;
+        for other in [other_uuid, other_driver] {
+            assert_eq!(initial_data(Some(&written(other, &data)), &header()), []);
+        }
+        assert_eq!(initial_data(Some(&data), &header()), []);
+        assert_eq!(
+            initial_data(Some(&bytes[..CacheHeader::LEN - 1]), &header()),
+            []
+        );
+        assert_eq!(initial_data(None, &header()), []);
+    }
+}
@@ -1,4 +1,6 @@
-use super::{RenderInfo, WindowedInstance};
+use super::error::Context;
+use super::screenshot::{self, PendingScreenshot, Screenshot};
+use super::{Error, RenderInfo, Result, SwapchainInfo, WindowedInstance};
 use crate::vk::Instance;
 use erupt::vk;
 
@@ -6,26 +8,77 @@ pub trait Renderable {
     fn render_info(&self) -> RenderInfo;
 }
 
-const MAX_FRAMES_IN_FLIGHT: u32 = 2;
+/// How finished frames are handed to the display. Modes the surface doesn't support fall back
+/// to `Fifo`, which is always available.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PresentMode {
+    /// Vsync, queueing frames.
+    Fifo,
+    /// Like `Fifo`, but a frame that misses its vblank is shown right away, tearing instead of
+    /// stuttering.
+    FifoRelaxed,
+    /// Vsync, replacing the queued frame with newer ones so rendering never blocks.
+    Mailbox,
+    /// No vsync. Lowest latency, but frames can tear.
+    Immediate,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SwapchainOptions {
+    pub present_mode: PresentMode,
+    /// Frames the CPU can record ahead of the GPU. More smooths out uneven frames, fewer cuts
+    /// input latency. At least 1.
+    pub frames_in_flight: u32,
+    /// Samples per pixel for multisampling, 1 for none. Lowered to the most the GPU supports,
+    /// and to a power of two.
+    pub samples: u32,
+    /// Clears depth to 0 and keeps fragments with greater depth, with an infinite far plane.
+    /// Spreads floating point depth precision evenly over distance, so faraway chunks don't
+    /// z-fight.
+    pub reversed_z: bool,
+}
 
 pub struct Swapchain {
     instance: std::sync::Arc<WindowedInstance>,
     render_pass: vk::RenderPass,
-    extent: vk::Extent2D,
+    surface_format: vk::SurfaceFormatKHR,
+    depth_format: vk::Format,
+    options: SwapchainOptions,
 
-    swapchain: vk::SwapchainKHR,
-    swapchain_framebuffers: Vec<SwapchainFramebuffer>,
-    depth_image: vk::Image,
-    depth_allocation: vk_alloc::Allocation,
-    depth_view: vk::ImageView,
+    images: SwapchainImages,
 
     command_pool: vk::CommandPool,
     sync_objects: Vec<RenderSyncObject>,
     current_frame: usize,
+
+    screenshot_requested: bool,
+    pending_screenshot: Option<PendingScreenshot>,
+    screenshot: Option<Screenshot>,
+}
+
+/// Everything that depends on the window size, recreated by [`Swapchain::rebuild`].
+struct SwapchainImages {
+    swapchain: vk::SwapchainKHR,
+    extent: vk::Extent2D,
+    framebuffers: Vec<SwapchainFramebuffer>,
+    depth: AttachmentImage,
+    /// Multisampled image rendered to and then resolved into the swapchain image, if
+    /// multisampling.
+    color: Option<AttachmentImage>,
+    /// Whether the images can be copied from, which screenshots need.
+    transfer_src: bool,
+}
+
+/// An image only used within the render pass, like the depth buffer.
+struct AttachmentImage {
+    image: vk::Image,
+    allocation: vk_alloc::Allocation,
+    view: vk::ImageView,
 }
 
 #[derive(Debug, Default, Copy, Clone)]
 struct SwapchainFramebuffer {
+    image: vk::Image,
     view: vk::ImageView,
     framebuffer: vk::Framebuffer,
     fence: vk::Fence,
@@ -39,26 +92,114 @@ struct RenderSyncObject {
     command_buffer: vk::CommandBuffer,
 }
 
+impl PresentMode {
+    pub(super) fn vk(&self) -> vk::PresentModeKHR {
+        match self {
+            Self::Fifo => vk::PresentModeKHR::FIFO_KHR,
+            Self::FifoRelaxed => vk::PresentModeKHR::FIFO_RELAXED_KHR,
+            Self::Mailbox => vk::PresentModeKHR::MAILBOX_KHR,
+            Self::Immediate => vk::PresentModeKHR::IMMEDIATE_KHR,
+        }
+    }
+}
+
+impl std::str::FromStr for PresentMode {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        match name {
+            "fifo" => Ok(Self::Fifo),
+            "fifo-relaxed" => Ok(Self::FifoRelaxed),
+            "mailbox" => Ok(Self::Mailbox),
+            "immediate" => Ok(Self::Immediate),
+            _ => Err(format!(
+                "unknown present mode {:?}, expected fifo, fifo-relaxed, mailbox or immediate",
+                name
+            )),
+        }
+    }
+}
+
+impl Default for SwapchainOptions {
+    fn default() -> Self {
+        Self {
+            present_mode: PresentMode::Mailbox,
+            frames_in_flight: 2,
+            samples: 1,
+            reversed_z: false,
+        }
+    }
+}
+
 impl Swapchain {
-    pub fn new(instance: std::sync::Arc<WindowedInstance>, size: (u32, u32)) -> Self {
+    pub fn new(
+        instance: std::sync::Arc<WindowedInstance>,
+        size: (u32, u32),
+        mut options: SwapchainOptions,
+    ) -> Result<Self> {
         let device = instance.device();
-        let allocator = instance.allocator();
-        let swapchain_info = instance.swapchain_info(size);
-        let (graphics, present) = (instance.graphics_queue(), instance.present_queue());
+        let swapchain_info = instance.swapchain_info(size, options.present_mode)?;
+        // Remember the fallback, so rebuilding doesn't warn about it again.
+        if swapchain_info.present_mode != options.present_mode.vk() {
+            options.present_mode = PresentMode::Fifo
+        }
+        options.frames_in_flight = options.frames_in_flight.max(1);
+        let supported_samples = instance.supported_samples();
+        let samples = [8, 4, 2, 1]
+            .iter()
+            .copied()
+            .find(|&count| {
+                count <= options.samples
+                    && supported_samples.contains(vk::SampleCountFlagBits(count).bitmask())
+            })
+            .unwrap_or(1);
+        if samples != options.samples {
+            log::warn!(
+                "{}x multisampling is not supported, using {}x. Supported: {:?}",
+                options.samples,
+                samples,
+                supported_samples
+            );
+            options.samples = samples
+        }
+        let samples = vk::SampleCountFlagBits(samples);
+        let depth_format = instance.depth_format()?;
+        let float_depth = matches!(
+            depth_format,
+            vk::Format::D32_SFLOAT | vk::Format::D32_SFLOAT_S8_UINT
+        );
+        if options.reversed_z && !float_depth {
+            log::warn!(
+                "reversed-Z only adds precision with floating point depth, which {:?} isn't",
+                depth_format
+            )
+        }
+        let multisampled = samples != vk::SampleCountFlagBits::_1;
+        let graphics = instance.graphics_queue();
 
-        let attachments = [
+        // With multisampling, the multisampled colour attachment is resolved into the
+        // swapchain image at the end. Without it, the swapchain image is rendered to directly.
+        let mut attachments = vec![
             vk::AttachmentDescriptionBuilder::new()
                 .format(swapchain_info.surface_format.format)
-                .samples(vk::SampleCountFlagBits::_1)
+                .samples(samples)
                 .load_op(vk::AttachmentLoadOp::CLEAR)
-                .store_op(vk::AttachmentStoreOp::STORE)
+                .store_op(if multisampled {
+                    vk::AttachmentStoreOp::DONT_CARE
+                } else {
+                    vk::AttachmentStoreOp::STORE
+                })
                 .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
                 .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
                 .initial_layout(vk::ImageLayout::UNDEFINED)
-                .final_layout(vk::ImageLayout::PRESENT_SRC_KHR),
+                .final_layout(if multisampled {
+                    vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+                } else {
+                    vk::ImageLayout::PRESENT_SRC_KHR
+                }),
             vk::AttachmentDescriptionBuilder::new()
-                .format(vk::Format::D32_SFLOAT)
-                .samples(vk::SampleCountFlagBits::_1)
+                .format(depth_format)
+                .samples(samples)
                 .load_op(vk::AttachmentLoadOp::CLEAR)
                 .store_op(vk::AttachmentStoreOp::DONT_CARE)
                 .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
@@ -66,16 +207,35 @@ impl Swapchain {
                 .initial_layout(vk::ImageLayout::UNDEFINED)
                 .final_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
         ];
+        if multisampled {
+            attachments.push(
+                vk::AttachmentDescriptionBuilder::new()
+                    .format(swapchain_info.surface_format.format)
+                    .samples(vk::SampleCountFlagBits::_1)
+                    .load_op(vk::AttachmentLoadOp::DONT_CARE)
+                    .store_op(vk::AttachmentStoreOp::STORE)
+                    .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                    .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                    .initial_layout(vk::ImageLayout::UNDEFINED)
+                    .final_layout(vk::ImageLayout::PRESENT_SRC_KHR),
+            )
+        }
         let depth_stencil_attachment = vk::AttachmentReferenceBuilder::new()
             .attachment(1)
             .layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
         let color_attachment = vk::AttachmentReferenceBuilder::new()
             .attachment(0)
             .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
-        let subpass = vk::SubpassDescriptionBuilder::new()
+        let resolve_attachment = vk::AttachmentReferenceBuilder::new()
+            .attachment(2)
+            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        let mut subpass = vk::SubpassDescriptionBuilder::new()
             .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
             .color_attachments(std::slice::from_ref(&color_attachment))
             .depth_stencil_attachment(&depth_stencil_attachment);
+        if multisampled {
+            subpass = subpass.resolve_attachments(std::slice::from_ref(&resolve_attachment))
+        }
         let dependency = vk::SubpassDependencyBuilder::new()
             .src_subpass(vk::SUBPASS_EXTERNAL)
             .dst_subpass(0)
@@ -96,184 +256,168 @@ impl Swapchain {
             .attachments(&attachments)
             .subpasses(std::slice::from_ref(&subpass))
             .dependencies(std::slice::from_ref(&dependency));
-        let render_pass =
-            unsafe { device.create_render_pass(&render_pass_create_info, None) }.unwrap();
+        let render_pass = unsafe { device.create_render_pass(&render_pass_create_info, None) }
+            .context("creating the render pass")?;
 
-        let (sharing_mode, queue_families) = if graphics.family == present.family {
-            (vk::SharingMode::EXCLUSIVE, Vec::new())
-        } else {
-            (
-                vk::SharingMode::CONCURRENT,
-                vec![graphics.family, present.family],
-            )
+        let images = match SwapchainImages::new(
+            &instance,
+            &swapchain_info,
+            render_pass,
+            depth_format,
+            samples,
+            None,
+        ) {
+            Ok(images) => images,
+            Err(err) => {
+                unsafe { device.destroy_render_pass(Some(render_pass), None) };
+                return Err(err);
+            }
         };
-        let min_image_count = (swapchain_info.surface_caps.min_image_count + 1)
-            .min(swapchain_info.surface_caps.max_image_count);
 
-        let swapchain_create_info = vk::SwapchainCreateInfoKHRBuilder::new()
-            .surface(swapchain_info.surface)
-            .min_image_count(min_image_count)
-            .image_format(swapchain_info.surface_format.format)
-            .image_color_space(swapchain_info.surface_format.color_space)
-            .image_extent(swapchain_info.extent)
-            .image_array_layers(1)
-            .image_usage(vk::ImageUsageFlags::COLOR_ATTACHMENT)
-            .image_sharing_mode(sharing_mode)
-            .queue_family_indices(&queue_families)
-            .pre_transform(swapchain_info.surface_caps.current_transform)
-            .composite_alpha(vk::CompositeAlphaFlagBitsKHR::OPAQUE_KHR)
-            .present_mode(swapchain_info.present_mode)
-            .clipped(true);
-        let swapchain =
-            unsafe { device.create_swapchain_khr(&swapchain_create_info, None) }.unwrap();
+        let mut swapchain = Self {
+            instance,
+            render_pass,
+            surface_format: swapchain_info.surface_format,
+            depth_format,
+            options,
 
-        let image_create_info = vk::ImageCreateInfoBuilder::new()
-            .image_type(vk::ImageType::_2D)
-            .format(vk::Format::D32_SFLOAT)
-            .extent(vk::Extent3D {
-                width: swapchain_info.extent.width,
-                height: swapchain_info.extent.height,
-                depth: 1,
-            })
-            .mip_levels(1)
-            .array_layers(1)
-            .samples(vk::SampleCountFlagBits::_1)
-            .tiling(vk::ImageTiling::OPTIMAL)
-            .usage(vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
-            .sharing_mode(vk::SharingMode::EXCLUSIVE)
-            .initial_layout(vk::ImageLayout::UNDEFINED);
-        let depth_image = unsafe { device.create_image(&image_create_info, None) }.unwrap();
-        let depth_image_allocation = allocator
-            .allocate_memory_for_image(&device, depth_image, vk_alloc::MemoryLocation::GpuOnly)
-            .unwrap();
-        unsafe {
-            device.bind_image_memory(
-                depth_image,
-                depth_image_allocation.device_memory,
-                depth_image_allocation.offset,
-            )
-        }
-        .unwrap();
-        let image_view_create_info = vk::ImageViewCreateInfoBuilder::new()
-            .image(depth_image)
-            .view_type(vk::ImageViewType::_2D)
-            .format(vk::Format::D32_SFLOAT)
-            .components(vk::ComponentMapping::default())
-            .subresource_range(vk::ImageSubresourceRange {
-                aspect_mask: vk::ImageAspectFlags::DEPTH,
-                base_mip_level: 0,
-                level_count: 1,
-                base_array_layer: 0,
-                layer_count: 1,
-            });
-        let depth_image_view =
-            unsafe { device.create_image_view(&image_view_create_info, None) }.unwrap();
-
-        let images = unsafe { device.get_swapchain_images_khr(swapchain, None) }.unwrap();
-        let swapchain_images = images
-            .into_iter()
-            .map(|image| {
-                let view_create_info = vk::ImageViewCreateInfoBuilder::new()
-                    .image(image)
-                    .view_type(vk::ImageViewType::_2D)
-                    .format(swapchain_info.surface_format.format)
-                    .components(vk::ComponentMapping::default())
-                    .subresource_range(vk::ImageSubresourceRange {
-                        aspect_mask: vk::ImageAspectFlags::COLOR,
-                        base_mip_level: 0,
-                        level_count: 1,
-                        base_array_layer: 0,
-                        layer_count: 1,
-                    });
-                let view = unsafe { device.create_image_view(&view_create_info, None) }.unwrap();
-                let attachments = [view, depth_image_view];
-                let framebuffer_create_info = vk::FramebufferCreateInfoBuilder::new()
-                    .render_pass(render_pass)
-                    .attachments(&attachments)
-                    .width(swapchain_info.extent.width)
-                    .height(swapchain_info.extent.height)
-                    .layers(1);
-                let framebuffer =
-                    unsafe { device.create_framebuffer(&framebuffer_create_info, None) }.unwrap();
-                SwapchainFramebuffer {
-                    view,
-                    framebuffer,
-                    fence: vk::Fence::null(),
-                }
-            })
-            .collect();
+            images,
+
+            command_pool: vk::CommandPool::null(),
+            sync_objects: Vec::new(),
+            current_frame: 0,
+
+            screenshot_requested: false,
+            pending_screenshot: None,
+            screenshot: None,
+        };
+        // Returning drops the swapchain, which frees whatever was created.
+        swapchain.create_frames(graphics.family)?;
+        Ok(swapchain)
+    }
+
+    /// Creates the command pool, and a command buffer, fence and semaphores for each frame in
+    /// flight. Anything created is in `self` even if this fails, so it's freed on drop.
+    fn create_frames(&mut self, queue_family: u32) -> Result<()> {
+        let device = self.instance.device();
 
         let command_pool_create_info = vk::CommandPoolCreateInfoBuilder::new()
             .flags(vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER)
-            .queue_family_index(graphics.family);
-        let command_pool =
-            unsafe { device.create_command_pool(&command_pool_create_info, None) }.unwrap();
+            .queue_family_index(queue_family);
+        self.command_pool = unsafe { device.create_command_pool(&command_pool_create_info, None) }
+            .context("creating the command pool")?;
 
         let command_buffer_allocate_info = vk::CommandBufferAllocateInfoBuilder::new()
-            .command_pool(command_pool)
+            .command_pool(self.command_pool)
             .level(vk::CommandBufferLevel::PRIMARY)
-            .command_buffer_count(MAX_FRAMES_IN_FLIGHT);
+            .command_buffer_count(self.options.frames_in_flight);
         let command_buffers =
-            unsafe { device.allocate_command_buffers(&command_buffer_allocate_info) }.unwrap();
+            unsafe { device.allocate_command_buffers(&command_buffer_allocate_info) }
+                .context("allocating command buffers")?;
         let semaphore_create_info = vk::SemaphoreCreateInfoBuilder::new();
         let fence_create_info =
             vk::FenceCreateInfoBuilder::new().flags(vk::FenceCreateFlags::SIGNALED);
-        let render_sync_objects = command_buffers
-            .into_iter()
-            .map(|command_buffer| {
-                let in_flight = unsafe { device.create_fence(&fence_create_info, None) }.unwrap();
-                let image_available =
-                    unsafe { device.create_semaphore(&semaphore_create_info, None) }.unwrap();
-                let render_finished =
-                    unsafe { device.create_semaphore(&semaphore_create_info, None) }.unwrap();
-                RenderSyncObject {
-                    in_flight,
-                    image_available,
-                    render_finished,
-                    command_buffer,
-                }
-            })
-            .collect();
-
-        Self {
-            instance,
-            render_pass,
-            extent: swapchain_info.extent,
+        for command_buffer in command_buffers {
+            // Null handles until they're created, which are fine to destroy.
+            self.sync_objects.push(RenderSyncObject {
+                command_buffer,
+                ..Default::default()
+            });
+            let sync = self.sync_objects.last_mut().unwrap();
+            sync.in_flight = unsafe { device.create_fence(&fence_create_info, None) }
+                .context("creating a fence")?;
+            sync.image_available = unsafe { device.create_semaphore(&semaphore_create_info, None) }
+                .context("creating a semaphore")?;
+            sync.render_finished = unsafe { device.create_semaphore(&semaphore_create_info, None) }
+                .context("creating a semaphore")?;
+        }
+        Ok(())
+    }
 
-            swapchain,
-            swapchain_framebuffers: swapchain_images,
-            depth_image,
-            depth_allocation: depth_image_allocation,
-            depth_view: depth_image_view,
+    /// Recreates the swapchain at `size`, e.g. after the window is resized. The GPU must be
+    /// done with the old one. The render pass stays the same, so pipelines made for it keep
+    /// working.
+    pub fn rebuild(&mut self, size: (u32, u32)) -> Result<()> {
+        let mut swapchain_info = self
+            .instance
+            .swapchain_info(size, self.options.present_mode)?;
+        // The surface is the same, so this only keeps the format from ever changing under the
+        // render pass.
+        swapchain_info.surface_format = self.surface_format;
+        let images = SwapchainImages::new(
+            &self.instance,
+            &swapchain_info,
+            self.render_pass,
+            self.depth_format,
+            vk::SampleCountFlagBits(self.options.samples),
+            Some(self.images.swapchain),
+        )?;
+        std::mem::replace(&mut self.images, images).destroy(&self.instance);
+        // The GPU is done, so a screenshot in flight is too.
+        self.finish_screenshot();
+        Ok(())
+    }
 
-            command_pool,
-            sync_objects: render_sync_objects,
-            current_frame: 0,
+    /// Captures the next frame rendered, to be picked up with [`Self::take_screenshot`] once
+    /// the GPU has finished it.
+    pub fn request_screenshot(&mut self) {
+        if !self.images.transfer_src {
+            log::warn!("swapchain images can't be copied from, so screenshots aren't supported")
+        } else if !screenshot::supports_format(self.surface_format.format) {
+            log::warn!(
+                "screenshots aren't supported with surface format {:?}",
+                self.surface_format.format
+            )
+        } else {
+            self.screenshot_requested = true
         }
     }
 
-    pub fn rebuild(&mut self, size: (u32, u32)) {
-        let instance = self.instance.clone();
-        unsafe {
-            std::mem::drop(std::ptr::read(self));
-            std::ptr::write(self, Self::new(instance, size))
+    /// The requested screenshot, once it's ready.
+    pub fn take_screenshot(&mut self) -> Option<Screenshot> {
+        self.screenshot.take()
+    }
+
+    fn finish_screenshot(&mut self) {
+        if let Some(pending) = self.pending_screenshot.take() {
+            match pending.finish(&self.instance) {
+                Ok(screenshot) => self.screenshot = Some(screenshot),
+                Err(err) => log::error!("failed to read the screenshot: {}", err),
+            }
         }
     }
 
-    pub fn render(&mut self, record: impl FnOnce(vk::CommandBuffer)) -> bool {
-        let device = self.instance.device();
+    /// Options in use, with the present mode and sample count it fell back to if the requested
+    /// ones weren't supported.
+    pub fn options(&self) -> SwapchainOptions {
+        self.options
+    }
+
+    /// Records a frame with `record`, which gets the command buffer and the current extent, and
+    /// presents it. Returns `false` if the swapchain no longer matches the window and has to
+    /// be rebuilt.
+    pub fn render(&mut self, record: impl FnOnce(vk::CommandBuffer, RenderInfo)) -> Result<bool> {
+        // A handle of its own, so finishing a screenshot can borrow `self` mutably.
+        let instance = self.instance.clone();
+        let device = instance.device();
         let (graphics, present) = (
             self.instance.graphics_queue(),
             self.instance.present_queue(),
         );
         self.current_frame = (self.current_frame + 1) % self.sync_objects.len();
-        let sync = &self.sync_objects[self.current_frame];
+        let sync = self.sync_objects[self.current_frame];
 
-        unsafe { device.wait_for_fences(&[sync.in_flight], true, u64::MAX) }.unwrap();
+        unsafe { device.wait_for_fences(&[sync.in_flight], true, u64::MAX) }
+            .context("waiting for a frame to finish")?;
+        if let Some(pending) = &self.pending_screenshot {
+            if pending.frame == self.current_frame {
+                self.finish_screenshot()
+            }
+        }
 
         let image_acquired = unsafe {
             device.acquire_next_image_khr(
-                self.swapchain,
+                self.images.swapchain,
                 u64::MAX,
                 Some(sync.image_available),
                 None,
@@ -282,56 +426,96 @@ impl Swapchain {
         let index = match image_acquired.result() {
             Ok(x) => x as usize,
             Err(vk::Result::SUBOPTIMAL_KHR | vk::Result::ERROR_OUT_OF_DATE_KHR) => {
-                return false;
+                return Ok(false);
             }
-            Err(e) => panic!("{}", e),
+            Err(result) => return Err(Error::from_vk("acquiring a swapchain image", result)),
         };
 
-        if !self.swapchain_framebuffers[index].fence.is_null() {
+        if !self.images.framebuffers[index].fence.is_null() {
             unsafe {
-                device.wait_for_fences(&[self.swapchain_framebuffers[index].fence], true, u64::MAX)
+                device.wait_for_fences(&[self.images.framebuffers[index].fence], true, u64::MAX)
             }
-            .unwrap()
+            .context("waiting for a swapchain image")?
         };
-        self.swapchain_framebuffers[index].fence = sync.in_flight;
+        self.images.framebuffers[index].fence = sync.in_flight;
 
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    float32: [0.0, 0.0, 0.0, 0.0],
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    // The far plane, which reversed-Z puts at 0.
+                    depth: if self.options.reversed_z { 0.0 } else { 1.0 },
+                    stencil: 0,
+                },
+            },
+        ];
         let render_pass_begin_info = vk::RenderPassBeginInfoBuilder::new()
             .render_pass(self.render_pass)
-            .framebuffer(self.swapchain_framebuffers[index].framebuffer)
+            .framebuffer(self.images.framebuffers[index].framebuffer)
             .render_area(vk::Rect2D {
                 offset: vk::Offset2D { x: 0, y: 0 },
-                extent: self.extent,
+                extent: self.images.extent,
             })
-            .clear_values(&[
-                vk::ClearValue {
-                    color: vk::ClearColorValue {
-                        float32: [0.0, 0.0, 0.0, 0.0],
-                    },
-                },
-                vk::ClearValue {
-                    depth_stencil: vk::ClearDepthStencilValue {
-                        depth: 1.0,
-                        stencil: 0,
-                    },
-                },
-            ]);
+            .clear_values(&clear_values);
         let command_buffer_begin_info = vk::CommandBufferBeginInfoBuilder::new()
             .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT);
         unsafe {
             device
                 .begin_command_buffer(sync.command_buffer, &command_buffer_begin_info)
-                .unwrap();
+                .context("recording a frame")?;
             device.cmd_begin_render_pass(
                 sync.command_buffer,
                 &render_pass_begin_info,
                 vk::SubpassContents::INLINE,
             );
-            record(sync.command_buffer);
+            // Pipelines take the viewport and scissor as dynamic state, so they don't depend on
+            // the window size.
+            device.cmd_set_viewport(
+                sync.command_buffer,
+                0,
+                &[vk::ViewportBuilder::new()
+                    .x(0.0)
+                    .y(0.0)
+                    .width(self.images.extent.width as f32)
+                    .height(self.images.extent.height as f32)
+                    .min_depth(0.0)
+                    .max_depth(1.0)],
+            );
+            device.cmd_set_scissor(
+                sync.command_buffer,
+                0,
+                &[vk::Rect2DBuilder::new()
+                    .offset(vk::Offset2D { x: 0, y: 0 })
+                    .extent(self.images.extent)],
+            );
+            record(sync.command_buffer, self.render_info());
             device.cmd_end_render_pass(sync.command_buffer);
-            device.end_command_buffer(sync.command_buffer).unwrap()
+        }
+        if self.screenshot_requested && self.pending_screenshot.is_none() {
+            self.screenshot_requested = false;
+            match PendingScreenshot::record(
+                &self.instance,
+                sync.command_buffer,
+                self.images.framebuffers[index].image,
+                self.images.extent,
+                self.surface_format.format,
+                self.current_frame,
+            ) {
+                Ok(pending) => self.pending_screenshot = Some(pending),
+                Err(err) => log::error!("failed to take a screenshot: {}", err),
+            }
+        }
+        unsafe {
+            device
+                .end_command_buffer(sync.command_buffer)
+                .context("recording a frame")?
         }
 
-        unsafe { device.reset_fences(&[sync.in_flight]) }.unwrap();
+        unsafe { device.reset_fences(&[sync.in_flight]) }.context("resetting a fence")?;
 
         let submit_info = vk::SubmitInfoBuilder::new()
             .wait_semaphores(std::slice::from_ref(&sync.image_available))
@@ -339,18 +523,18 @@ impl Swapchain {
             .command_buffers(std::slice::from_ref(&sync.command_buffer))
             .signal_semaphores(std::slice::from_ref(&sync.render_finished));
         unsafe { device.queue_submit(graphics.queue, &[submit_info], Some(sync.in_flight)) }
-            .unwrap();
+            .context("submitting a frame")?;
 
         let image_index = index as u32;
         let present_info = vk::PresentInfoKHRBuilder::new()
             .wait_semaphores(std::slice::from_ref(&sync.render_finished))
-            .swapchains(std::slice::from_ref(&self.swapchain))
+            .swapchains(std::slice::from_ref(&self.images.swapchain))
             .image_indices(std::slice::from_ref(&image_index));
         let presented = unsafe { device.queue_present_khr(present.queue, &present_info) };
         match presented.result() {
-            Ok(()) => true,
-            Err(vk::Result::SUBOPTIMAL_KHR | vk::Result::ERROR_OUT_OF_DATE_KHR) => false,
-            Err(e) => panic!("{}", e),
+            Ok(()) => Ok(true),
+            Err(vk::Result::SUBOPTIMAL_KHR | vk::Result::ERROR_OUT_OF_DATE_KHR) => Ok(false),
+            Err(result) => Err(Error::from_vk("presenting a frame", result)),
         }
     }
 }
@@ -359,7 +543,9 @@ impl Renderable for Swapchain {
     fn render_info(&self) -> RenderInfo {
         RenderInfo {
             render_pass: self.render_pass,
-            extent: self.extent,
+            extent: self.images.extent,
+            samples: vk::SampleCountFlagBits(self.options.samples),
+            reversed_z: self.options.reversed_z,
         }
     }
 }
@@ -367,8 +553,11 @@ impl Renderable for Swapchain {
 impl Drop for Swapchain {
     fn drop(&mut self) {
         let device = self.instance.device();
-        let allocator = self.instance.allocator();
 
+        self.images.destroy(&self.instance);
+        if let Some(pending) = &self.pending_screenshot {
+            pending.destroy(&self.instance)
+        }
         unsafe {
             for render_sync_object in &self.sync_objects {
                 device.destroy_fence(Some(render_sync_object.in_flight), None);
@@ -376,17 +565,276 @@ impl Drop for Swapchain {
                 device.destroy_semaphore(Some(render_sync_object.render_finished), None)
             }
             device.destroy_command_pool(Some(self.command_pool), None);
-            for swapchain_image in &self.swapchain_framebuffers {
-                device.destroy_framebuffer(Some(swapchain_image.framebuffer), None);
-                device.destroy_image_view(Some(swapchain_image.view), None)
-            }
-            device.destroy_image_view(Some(self.depth_view), None);
-            allocator
-                .deallocate(device, &self.depth_allocation)
-                .unwrap();
-            device.destroy_image(Some(self.depth_image), None);
-            device.destroy_swapchain_khr(Some(self.swapchain), None);
             device.destroy_render_pass(Some(self.render_pass), None)
         }
     }
 }
+
+impl SwapchainImages {
+    /// Creates the swapchain, handing the window over from `old_swapchain` if there is one.
+    fn new(
+        instance: &WindowedInstance,
+        swapchain_info: &SwapchainInfo,
+        render_pass: vk::RenderPass,
+        depth_format: vk::Format,
+        samples: vk::SampleCountFlagBits,
+        old_swapchain: Option<vk::SwapchainKHR>,
+    ) -> Result<Self> {
+        let device = instance.device();
+        let (graphics, present) = (instance.graphics_queue(), instance.present_queue());
+
+        let (sharing_mode, queue_families) = if graphics.family == present.family {
+            (vk::SharingMode::EXCLUSIVE, Vec::new())
+        } else {
+            (
+                vk::SharingMode::CONCURRENT,
+                vec![graphics.family, present.family],
+            )
+        };
+        let min_image_count = (swapchain_info.surface_caps.min_image_count + 1)
+            .min(swapchain_info.surface_caps.max_image_count);
+
+        let transfer_src = swapchain_info
+            .surface_caps
+            .supported_usage_flags
+            .contains(vk::ImageUsageFlags::TRANSFER_SRC);
+        let image_usage = if transfer_src {
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC
+        } else {
+            vk::ImageUsageFlags::COLOR_ATTACHMENT
+        };
+
+        let swapchain_create_info = vk::SwapchainCreateInfoKHRBuilder::new()
+            .surface(swapchain_info.surface)
+            .min_image_count(min_image_count)
+            .image_format(swapchain_info.surface_format.format)
+            .image_color_space(swapchain_info.surface_format.color_space)
+            .image_extent(swapchain_info.extent)
+            .image_array_layers(1)
+            .image_usage(image_usage)
+            .image_sharing_mode(sharing_mode)
+            .queue_family_indices(&queue_families)
+            .pre_transform(swapchain_info.surface_caps.current_transform)
+            .composite_alpha(vk::CompositeAlphaFlagBitsKHR::OPAQUE_KHR)
+            .present_mode(swapchain_info.present_mode)
+            .clipped(true)
+            .old_swapchain(old_swapchain.unwrap_or_default());
+        let swapchain = unsafe { device.create_swapchain_khr(&swapchain_create_info, None) }
+            .context("creating the swapchain")?;
+
+        let depth = match AttachmentImage::new(
+            instance,
+            swapchain_info.extent,
+            depth_format,
+            samples,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            super::instance::depth_aspect(depth_format),
+        ) {
+            Ok(depth) => depth,
+            Err(err) => {
+                unsafe { device.destroy_swapchain_khr(Some(swapchain), None) };
+                return Err(err);
+            }
+        };
+        let color = if samples == vk::SampleCountFlagBits::_1 {
+            None
+        } else {
+            match AttachmentImage::new(
+                instance,
+                swapchain_info.extent,
+                swapchain_info.surface_format.format,
+                samples,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSIENT_ATTACHMENT,
+                vk::ImageAspectFlags::COLOR,
+            ) {
+                Ok(color) => Some(color),
+                Err(err) => {
+                    depth.destroy(instance);
+                    unsafe { device.destroy_swapchain_khr(Some(swapchain), None) };
+                    return Err(err);
+                }
+            }
+        };
+
+        let mut images = Self {
+            swapchain,
+            extent: swapchain_info.extent,
+            framebuffers: Vec::new(),
+            depth,
+            color,
+            transfer_src,
+        };
+        if let Err(err) =
+            images.create_framebuffers(device, swapchain_info.surface_format.format, render_pass)
+        {
+            images.destroy(instance);
+            return Err(err);
+        }
+        Ok(images)
+    }
+
+    /// Creates a view and framebuffer for each swapchain image. The ones made before an error
+    /// are kept, so [`Self::destroy`] frees them.
+    fn create_framebuffers(
+        &mut self,
+        device: &erupt::DeviceLoader,
+        format: vk::Format,
+        render_pass: vk::RenderPass,
+    ) -> Result<()> {
+        let images = unsafe { device.get_swapchain_images_khr(self.swapchain, None) }
+            .context("getting swapchain images")?;
+        for image in images {
+            let view_create_info = vk::ImageViewCreateInfoBuilder::new()
+                .image(image)
+                .view_type(vk::ImageViewType::_2D)
+                .format(format)
+                .components(vk::ComponentMapping::default())
+                .subresource_range(vk::ImageSubresourceRange {
+                    aspect_mask: vk::ImageAspectFlags::COLOR,
+                    base_mip_level: 0,
+                    level_count: 1,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                });
+            let view = unsafe { device.create_image_view(&view_create_info, None) }
+                .context("creating a swapchain image view")?;
+            // In the order of the render pass attachments.
+            let attachments = match &self.color {
+                Some(color) => vec![color.view, self.depth.view, view],
+                None => vec![view, self.depth.view],
+            };
+            let framebuffer_create_info = vk::FramebufferCreateInfoBuilder::new()
+                .render_pass(render_pass)
+                .attachments(&attachments)
+                .width(self.extent.width)
+                .height(self.extent.height)
+                .layers(1);
+            let framebuffer =
+                match unsafe { device.create_framebuffer(&framebuffer_create_info, None) }
+                    .context("creating a framebuffer")
+                {
+                    Ok(framebuffer) => framebuffer,
+                    Err(err) => {
+                        unsafe { device.destroy_image_view(Some(view), None) };
+                        return Err(err);
+                    }
+                };
+            self.framebuffers.push(SwapchainFramebuffer {
+                image,
+                view,
+                framebuffer,
+                fence: vk::Fence::null(),
+            })
+        }
+        Ok(())
+    }
+
+    fn destroy(&self, instance: &WindowedInstance) {
+        let device = instance.device();
+
+        unsafe {
+            for framebuffer in &self.framebuffers {
+                device.destroy_framebuffer(Some(framebuffer.framebuffer), None);
+                device.destroy_image_view(Some(framebuffer.view), None)
+            }
+        }
+        self.depth.destroy(instance);
+        if let Some(color) = &self.color {
+            color.destroy(instance)
+        }
+        unsafe { device.destroy_swapchain_khr(Some(self.swapchain), None) }
+    }
+}
+
+impl AttachmentImage {
+    fn new(
+        instance: &WindowedInstance,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        samples: vk::SampleCountFlagBits,
+        usage: vk::ImageUsageFlags,
+        aspect_mask: vk::ImageAspectFlags,
+    ) -> Result<Self> {
+        let device = instance.device();
+        let allocator = instance.allocator();
+
+        let image_create_info = vk::ImageCreateInfoBuilder::new()
+            .image_type(vk::ImageType::_2D)
+            .format(format)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(samples)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+        let image = unsafe { device.create_image(&image_create_info, None) }
+            .context("creating an attachment image")?;
+        let allocation = match allocator
+            .allocate_memory_for_image(device, image, vk_alloc::MemoryLocation::GpuOnly)
+            .context("allocating an attachment image")
+        {
+            Ok(allocation) => allocation,
+            Err(err) => {
+                unsafe { device.destroy_image(Some(image), None) };
+                return Err(err);
+            }
+        };
+        let mut attachment = Self {
+            image,
+            allocation,
+            view: vk::ImageView::null(),
+        };
+        if let Err(err) = attachment.create_view(device, format, aspect_mask) {
+            attachment.destroy(instance);
+            return Err(err);
+        }
+        Ok(attachment)
+    }
+
+    fn create_view(
+        &mut self,
+        device: &erupt::DeviceLoader,
+        format: vk::Format,
+        aspect_mask: vk::ImageAspectFlags,
+    ) -> Result<()> {
+        unsafe {
+            device.bind_image_memory(
+                self.image,
+                self.allocation.device_memory,
+                self.allocation.offset,
+            )
+        }
+        .context("binding attachment image memory")?;
+        let image_view_create_info = vk::ImageViewCreateInfoBuilder::new()
+            .image(self.image)
+            .view_type(vk::ImageViewType::_2D)
+            .format(format)
+            .components(vk::ComponentMapping::default())
+            .subresource_range(vk::ImageSubresourceRange {
+                aspect_mask,
+                base_mip_level: 0,
+                level_count: 1,
+                base_array_layer: 0,
+                layer_count: 1,
+            });
+        self.view = unsafe { device.create_image_view(&image_view_create_info, None) }
+            .context("creating an attachment image view")?;
+        Ok(())
+    }
+
+    fn destroy(&self, instance: &WindowedInstance) {
+        let device = instance.device();
+        let allocator = instance.allocator();
+        unsafe { device.destroy_image_view(Some(self.view), None) };
+        if let Err(err) = allocator.deallocate(device, &self.allocation) {
+            log::error!("failed to free an attachment image: {}", err)
+        }
+        unsafe { device.destroy_image(Some(self.image), None) }
+    }
+}
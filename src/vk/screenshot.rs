@@ -0,0 +1,205 @@
+use super::error::Context;
+use super::{Error, Instance, Result, WindowedInstance};
+use erupt::vk;
+
+/// A captured frame as the swapchain stored it, 4 bytes per pixel, row by row from the top
+/// left. sRGB formats already hold sRGB encoded bytes, so only the channel order can differ
+/// from what image files expect.
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    /// Whether pixels are BGRA instead of RGBA.
+    pub bgra: bool,
+}
+
+/// A copy of a swapchain image into host memory that the GPU hasn't finished yet.
+pub(super) struct PendingScreenshot {
+    buffer: vk::Buffer,
+    allocation: vk_alloc::Allocation,
+    extent: vk::Extent2D,
+    format: vk::Format,
+    /// Frame whose fence signals that the copy is done.
+    pub(super) frame: usize,
+}
+
+/// Whether [`Screenshot`]s can be converted from images of `format`.
+pub(super) fn supports_format(format: vk::Format) -> bool {
+    matches!(
+        format,
+        vk::Format::B8G8R8A8_UNORM
+            | vk::Format::B8G8R8A8_SRGB
+            | vk::Format::R8G8B8A8_UNORM
+            | vk::Format::R8G8B8A8_SRGB
+    )
+}
+
+impl PendingScreenshot {
+    /// Records copying `image`, which the render pass left ready to present, into a new host
+    /// visible buffer, and hands the image back for presenting.
+    pub(super) fn record(
+        instance: &WindowedInstance,
+        command_buffer: vk::CommandBuffer,
+        image: vk::Image,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        frame: usize,
+    ) -> Result<Self> {
+        let device = instance.device();
+        let allocator = instance.allocator();
+
+        let buffer_info = vk::BufferCreateInfoBuilder::new()
+            .size(4 * extent.width as vk::DeviceSize * extent.height as vk::DeviceSize)
+            .usage(vk::BufferUsageFlags::TRANSFER_DST)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE);
+        let buffer = unsafe { device.create_buffer(&buffer_info, None) }
+            .context("creating a screenshot buffer")?;
+        let allocation = match allocator
+            .allocate_memory_for_buffer(device, buffer, vk_alloc::MemoryLocation::GpuToCpu)
+            .context("allocating a screenshot buffer")
+        {
+            Ok(allocation) => allocation,
+            Err(err) => {
+                unsafe { device.destroy_buffer(Some(buffer), None) };
+                return Err(err);
+            }
+        };
+        let pending = Self {
+            buffer,
+            allocation,
+            extent,
+            format,
+            frame,
+        };
+        if let Err(err) = unsafe {
+            device.bind_buffer_memory(
+                buffer,
+                pending.allocation.device_memory,
+                pending.allocation.offset,
+            )
+        }
+        .context("binding screenshot buffer memory")
+        {
+            pending.destroy(instance);
+            return Err(err);
+        }
+
+        let subresource_range = vk::ImageSubresourceRange {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            base_mip_level: 0,
+            level_count: 1,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let to_transfer = vk::ImageMemoryBarrierBuilder::new()
+            .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .old_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range);
+        let to_present = vk::ImageMemoryBarrierBuilder::new()
+            .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .dst_access_mask(vk::AccessFlags::empty())
+            .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range);
+        let to_host = vk::BufferMemoryBarrierBuilder::new()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE);
+        let region = vk::BufferImageCopyBuilder::new()
+            .buffer_offset(0)
+            // Zero means tightly packed rows.
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+            .image_extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            });
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                None,
+                &[],
+                &[],
+                &[to_transfer],
+            );
+            device.cmd_copy_image_to_buffer(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer,
+                &[region],
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE | vk::PipelineStageFlags::HOST,
+                None,
+                &[],
+                &[to_host],
+                &[to_present],
+            )
+        }
+        Ok(pending)
+    }
+
+    /// Copies the image out of the buffer and frees it. The GPU has to be done with the frame
+    /// it was recorded in.
+    pub(super) fn finish(self, instance: &WindowedInstance) -> Result<Screenshot> {
+        let screenshot = self.read();
+        self.destroy(instance);
+        screenshot
+    }
+
+    fn read(&self) -> Result<Screenshot> {
+        let len = 4 * self.extent.width as usize * self.extent.height as usize;
+        let bytes = self
+            .allocation
+            .mapped_slice()
+            .context("mapping a screenshot buffer")?
+            .ok_or(Error::Vulkan {
+                context: "mapping a screenshot buffer",
+                result: vk::Result::ERROR_MEMORY_MAP_FAILED,
+            })?;
+        Ok(Screenshot {
+            width: self.extent.width,
+            height: self.extent.height,
+            pixels: bytes[..len].to_vec(),
+            bgra: matches!(
+                self.format,
+                vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB
+            ),
+        })
+    }
+
+    pub(super) fn destroy(&self, instance: &WindowedInstance) {
+        let device = instance.device();
+        let allocator = instance.allocator();
+        if let Err(err) = allocator.deallocate(device, &self.allocation) {
+            log::error!("failed to free a screenshot buffer: {}", err)
+        }
+        unsafe { device.destroy_buffer(Some(self.buffer), None) }
+    }
+}
@@ -0,0 +1,244 @@
+use super::error::Context;
+use super::{Instance, RenderInfo, Renderable, Result, Shader, ShaderPair};
+use crate::voxel::VoxelBox;
+use erupt::vk;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct PushConstants {
+    transform: uv::Mat4,
+    color: uv::Vec4,
+}
+
+/// Draws the edges of a [`VoxelBox`], e.g. the editor selection.
+pub struct SelectionBoxRenderer<T: Instance> {
+    instance: std::sync::Arc<T>,
+    layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+    v_fov: f32,
+    shaders: ShaderPair,
+}
+
+impl<T: Instance> SelectionBoxRenderer<T> {
+    /// Vertical field of view in radians, until [`Self::set_v_fov`] is called.
+    const DEFAULT_V_FOV: f32 = std::f32::consts::FRAC_PI_3;
+    const SELECTION_VERT_SPV_BYTES: &'static [u8] = include_shader!("selection.vert");
+    const SELECTION_FRAG_SPV_BYTES: &'static [u8] = include_shader!("selection.frag");
+
+    /// How far the wireframe sits outside the box, so it doesn't z-fight with voxel faces.
+    const MARGIN: f32 = 0.01;
+
+    pub fn new(instance: std::sync::Arc<T>, surface: &impl Renderable) -> Result<Self> {
+        let shaders = ShaderPair {
+            vert: Shader::embedded("selection.vert", Self::SELECTION_VERT_SPV_BYTES),
+            frag: Shader::embedded("selection.frag", Self::SELECTION_FRAG_SPV_BYTES),
+        };
+        Self::create(instance, surface, shaders)
+    }
+
+    fn create(
+        instance: std::sync::Arc<T>,
+        surface: &impl Renderable,
+        shaders: ShaderPair,
+    ) -> Result<Self> {
+        let device = instance.device();
+        let render_info = surface.render_info();
+
+        let (vert_shader_module, frag_shader_module) = shaders.modules(device)?;
+
+        let entry_point = std::ffi::CString::new("main").unwrap();
+
+        let stages = [
+            vk::PipelineShaderStageCreateInfoBuilder::new()
+                .stage(vk::ShaderStageFlagBits::VERTEX)
+                .module(vert_shader_module)
+                .name(&entry_point),
+            vk::PipelineShaderStageCreateInfoBuilder::new()
+                .stage(vk::ShaderStageFlagBits::FRAGMENT)
+                .module(frag_shader_module)
+                .name(&entry_point),
+        ];
+
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfoBuilder::new();
+
+        let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfoBuilder::new()
+            .topology(vk::PrimitiveTopology::LINE_LIST)
+            .primitive_restart_enable(false);
+
+        // Set when recording, so resizing doesn't need a new pipeline.
+        let viewport_state = vk::PipelineViewportStateCreateInfoBuilder::new()
+            .viewport_count(1)
+            .scissor_count(1);
+        let dynamic_state = vk::PipelineDynamicStateCreateInfoBuilder::new()
+            .dynamic_states(&[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]);
+
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfoBuilder::new()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(vk::PolygonMode::FILL)
+            .cull_mode(vk::CullModeFlags::NONE)
+            .front_face(vk::FrontFace::CLOCKWISE)
+            .depth_bias_enable(false)
+            .line_width(1.0);
+
+        let multisample_state = vk::PipelineMultisampleStateCreateInfoBuilder::new()
+            .rasterization_samples(render_info.samples)
+            .sample_shading_enable(false)
+            .alpha_to_coverage_enable(false)
+            .alpha_to_one_enable(false);
+
+        let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfoBuilder::new()
+            .depth_test_enable(true)
+            .depth_write_enable(false)
+            .depth_compare_op(render_info.depth_compare_op())
+            .depth_bounds_test_enable(false)
+            .stencil_test_enable(false);
+
+        let color_blend_attachment = vk::PipelineColorBlendAttachmentStateBuilder::new()
+            .blend_enable(false)
+            .color_write_mask(vk::ColorComponentFlags::all());
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfoBuilder::new()
+            .logic_op_enable(false)
+            .attachments(std::slice::from_ref(&color_blend_attachment))
+            .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+        let push_constant_range = vk::PushConstantRangeBuilder::new()
+            .stage_flags(vk::ShaderStageFlags::VERTEX)
+            .offset(0)
+            .size(std::mem::size_of::<PushConstants>() as u32);
+
+        let layout_create_info = vk::PipelineLayoutCreateInfoBuilder::new()
+            .push_constant_ranges(std::slice::from_ref(&push_constant_range));
+
+        let layout = unsafe { device.create_pipeline_layout(&layout_create_info, None) }
+            .context("creating a pipeline layout");
+        let layout = match layout {
+            Ok(layout) => layout,
+            Err(err) => {
+                unsafe {
+                    device.destroy_shader_module(Some(vert_shader_module), None);
+                    device.destroy_shader_module(Some(frag_shader_module), None);
+                }
+                return Err(err);
+            }
+        };
+
+        let pipeline_create_info = vk::GraphicsPipelineCreateInfoBuilder::new()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input_state)
+            .input_assembly_state(&input_assembly_state)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterization_state)
+            .multisample_state(&multisample_state)
+            .depth_stencil_state(&depth_stencil_state)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .render_pass(render_info.render_pass)
+            .subpass(0);
+
+        let pipeline = unsafe {
+            device.create_graphics_pipelines(
+                Some(instance.pipeline_cache()),
+                &[pipeline_create_info],
+                None,
+            )
+        }
+        .context("creating a graphics pipeline");
+
+        unsafe {
+            device.destroy_shader_module(Some(vert_shader_module), None);
+            device.destroy_shader_module(Some(frag_shader_module), None);
+        }
+
+        let pipeline = match pipeline {
+            Ok(pipelines) => pipelines[0],
+            Err(err) => {
+                unsafe { device.destroy_pipeline_layout(Some(layout), None) };
+                return Err(err);
+            }
+        };
+
+        Ok(Self {
+            instance,
+            layout,
+            pipeline,
+            v_fov: Self::DEFAULT_V_FOV,
+            shaders,
+        })
+    }
+
+    /// Replaces the shader with file name `name`, e.g. `selection.frag`, and rebuilds the
+    /// pipeline, which must not be in use. Returns `false` if this renderer doesn't use that
+    /// shader. On failure the old shader and pipeline stay in use.
+    pub fn set_shader(
+        &mut self,
+        name: &str,
+        spirv: &[u8],
+        surface: &impl Renderable,
+    ) -> Result<bool> {
+        let shaders = match self.shaders.replaced(name, spirv) {
+            Some(shaders) => shaders,
+            None => return Ok(false),
+        };
+        let v_fov = self.v_fov;
+        *self = Self::create(self.instance.clone(), surface, shaders)?;
+        self.v_fov = v_fov;
+        Ok(true)
+    }
+
+    /// Sets the vertical field of view, in degrees.
+    pub fn set_v_fov(&mut self, v_fov: f32) {
+        self.v_fov = v_fov.to_radians()
+    }
+
+    pub fn render(
+        &mut self,
+        command_buffer: vk::CommandBuffer,
+        render_info: RenderInfo,
+        bounds: VoxelBox,
+        model_mat: uv::Mat4,
+        color: uv::Vec4,
+        camera: &crate::camera::Camera,
+    ) {
+        let device = self.instance.device();
+        // Voxels are centered on their coordinates, so the box spans half a voxel past them.
+        let min = bounds.min.pos() - uv::Vec3::broadcast(0.5 + Self::MARGIN);
+        let size =
+            bounds.max.pos() - bounds.min.pos() + uv::Vec3::broadcast(1.0 + 2.0 * Self::MARGIN);
+        let push_constants = PushConstants {
+            transform: render_info.perspective_mat(self.v_fov)
+                * camera.look_mat()
+                * model_mat
+                * uv::Mat4::from_translation(min)
+                * uv::Mat4::from_nonuniform_scale(size),
+            color,
+        };
+        unsafe {
+            device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline,
+            );
+            device.cmd_push_constants(
+                command_buffer,
+                self.layout,
+                vk::ShaderStageFlags::VERTEX,
+                0,
+                std::mem::size_of::<PushConstants>() as u32,
+                (&push_constants as *const PushConstants).cast(),
+            );
+            device.cmd_draw(command_buffer, 24, 1, 0, 0)
+        }
+    }
+}
+
+impl<T: Instance> Drop for SelectionBoxRenderer<T> {
+    fn drop(&mut self) {
+        let device = self.instance.device();
+        unsafe {
+            device.destroy_pipeline_layout(Some(self.layout), None);
+            device.destroy_pipeline(Some(self.pipeline), None)
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use super::{Instance, Renderable};
+use super::error::Context;
+use super::{Error, Instance, RenderInfo, Renderable, Result, Shader, ShaderPair};
 use crate::voxel::{ChunkCoord, Mesh, MeshFace};
 use erupt::vk;
 
@@ -18,12 +19,13 @@ pub struct VoxelMeshRenderer<T: Instance> {
     instance: std::sync::Arc<T>,
     layout: vk::PipelineLayout,
     pipeline: vk::Pipeline,
-    extent: vk::Extent2D,
     v_fov: f32,
+    wireframe: bool,
+    shaders: ShaderPair,
 }
 
 impl VoxelMeshBuffer {
-    pub fn new(instance: &impl Instance, mesh: &Mesh) -> Self {
+    pub fn new(instance: &impl Instance, mesh: &Mesh) -> Result<Self> {
         let device = instance.device();
         let allocator = instance.allocator();
 
@@ -32,18 +34,57 @@ impl VoxelMeshBuffer {
             .usage(vk::BufferUsageFlags::VERTEX_BUFFER)
             .sharing_mode(vk::SharingMode::EXCLUSIVE);
 
-        let vertex_buffer = unsafe { device.create_buffer(&buffer_info, None) }.unwrap();
+        let vertex_buffer = unsafe { device.create_buffer(&buffer_info, None) }
+            .context("creating a mesh buffer")?;
 
-        let mut allocation = allocator
+        let mut allocation = match allocator
             .allocate_memory_for_buffer(device, vertex_buffer, vk_alloc::MemoryLocation::CpuToGpu)
-            .unwrap();
+            .context("allocating a mesh buffer")
+        {
+            Ok(allocation) => allocation,
+            Err(err) => {
+                unsafe { device.destroy_buffer(Some(vertex_buffer), None) };
+                return Err(err);
+            }
+        };
+
+        if let Err(err) = Self::fill(device, vertex_buffer, &mut allocation, mesh) {
+            let buffer = Self {
+                vertex_buffer,
+                allocation,
+                length: 0,
+                mat: uv::Mat4::identity(),
+            };
+            buffer.destroy(instance);
+            return Err(err);
+        }
+
+        Ok(Self {
+            vertex_buffer,
+            allocation,
+            length: mesh.faces.len() as u32,
+            mat: mesh.coord.mat(),
+        })
+    }
 
+    fn fill(
+        device: &erupt::DeviceLoader,
+        vertex_buffer: vk::Buffer,
+        allocation: &mut vk_alloc::Allocation,
+        mesh: &Mesh,
+    ) -> Result<()> {
         unsafe {
             device.bind_buffer_memory(vertex_buffer, allocation.device_memory, allocation.offset)
         }
-        .unwrap();
+        .context("binding mesh buffer memory")?;
 
-        let slice = allocation.mapped_slice_mut().unwrap().unwrap();
+        let slice = allocation
+            .mapped_slice_mut()
+            .context("mapping a mesh buffer")?
+            .ok_or(Error::Vulkan {
+                context: "mapping a mesh buffer",
+                result: vk::Result::ERROR_MEMORY_MAP_FAILED,
+            })?;
 
         unsafe {
             std::ptr::copy_nonoverlapping(
@@ -52,22 +93,16 @@ impl VoxelMeshBuffer {
                 mesh.faces.len(),
             )
         };
-
-        Self {
-            vertex_buffer,
-            allocation,
-            length: mesh.faces.len() as u32,
-            mat: mesh.coord.mat(),
-        }
+        Ok(())
     }
 
     pub fn destroy(&self, instance: &impl Instance) {
         let device = instance.device();
         let allocator = instance.allocator();
-        unsafe {
-            allocator.deallocate(device, &self.allocation).unwrap();
-            device.destroy_buffer(Some(self.vertex_buffer), None);
+        if let Err(err) = allocator.deallocate(device, &self.allocation) {
+            log::error!("failed to free a mesh buffer: {}", err)
         }
+        unsafe { device.destroy_buffer(Some(self.vertex_buffer), None) }
     }
 }
 
@@ -87,11 +122,25 @@ impl<T: Instance> VoxelMeshManager<T> {
         self.meshes.len()
     }
 
-    pub fn upload_mesh(&mut self, mesh: &Mesh) {
-        let new_mesh = VoxelMeshBuffer::new(self.instance.as_ref(), mesh);
+    /// Replaces the mesh of a chunk. Meshes that may still be in use by the GPU must not be
+    /// replaced, so wait for idle first. If the upload fails the old mesh is kept.
+    pub fn upload_mesh(&mut self, mesh: &Mesh) -> Result<()> {
+        if mesh.faces.is_empty() {
+            // Zero-sized buffers aren't allowed, and there is nothing to draw anyway.
+            self.remove_mesh(mesh.coord);
+            return Ok(());
+        }
+        let new_mesh = VoxelMeshBuffer::new(self.instance.as_ref(), mesh)?;
         if let Some(old_mesh) = self.meshes.insert(mesh.coord, new_mesh) {
             old_mesh.destroy(self.instance.as_ref())
         }
+        Ok(())
+    }
+
+    pub fn remove_mesh(&mut self, coord: ChunkCoord) {
+        if let Some(old_mesh) = self.meshes.remove(&coord) {
+            old_mesh.destroy(self.instance.as_ref())
+        }
     }
 }
 
@@ -104,24 +153,38 @@ impl<T: Instance> Drop for VoxelMeshManager<T> {
 }
 
 impl<T: Instance> VoxelMeshRenderer<T> {
+    /// Vertical field of view in radians, until [`Self::set_v_fov`] is called.
+    const DEFAULT_V_FOV: f32 = std::f32::consts::FRAC_PI_3;
     const VOXEL_VERT_SPV_BYTES: &'static [u8] = include_shader!("voxel.vert");
     const VOXEL_FRAG_SPV_BYTES: &'static [u8] = include_shader!("voxel.frag");
 
-    pub fn new(instance: std::sync::Arc<T>, surface: &impl Renderable) -> Self {
+    pub fn new(instance: std::sync::Arc<T>, surface: &impl Renderable) -> Result<Self> {
+        Self::with_wireframe(instance, surface, true)
+    }
+
+    /// Renderer drawing only the edges of faces if `wireframe` is set, or filled faces if not.
+    pub fn with_wireframe(
+        instance: std::sync::Arc<T>,
+        surface: &impl Renderable,
+        wireframe: bool,
+    ) -> Result<Self> {
+        let shaders = ShaderPair {
+            vert: Shader::embedded("voxel.vert", Self::VOXEL_VERT_SPV_BYTES),
+            frag: Shader::embedded("voxel.frag", Self::VOXEL_FRAG_SPV_BYTES),
+        };
+        Self::create(instance, surface, wireframe, shaders)
+    }
+
+    fn create(
+        instance: std::sync::Arc<T>,
+        surface: &impl Renderable,
+        wireframe: bool,
+        shaders: ShaderPair,
+    ) -> Result<Self> {
         let device = instance.device();
         let render_info = surface.render_info();
 
-        let vert_code = erupt::utils::decode_spv(Self::VOXEL_VERT_SPV_BYTES).unwrap();
-        let vert_shader_module_create_info =
-            vk::ShaderModuleCreateInfoBuilder::new().code(&vert_code);
-        let vert_shader_module =
-            unsafe { device.create_shader_module(&vert_shader_module_create_info, None) }.unwrap();
-
-        let frag_code = erupt::utils::decode_spv(Self::VOXEL_FRAG_SPV_BYTES).unwrap();
-        let frag_shader_module_create_info =
-            vk::ShaderModuleCreateInfoBuilder::new().code(&frag_code);
-        let frag_shader_module =
-            unsafe { device.create_shader_module(&frag_shader_module_create_info, None) }.unwrap();
+        let (vert_shader_module, frag_shader_module) = shaders.modules(device)?;
 
         let entry_point = std::ffi::CString::new("main").unwrap();
 
@@ -153,31 +216,28 @@ impl<T: Instance> VoxelMeshRenderer<T> {
             .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
             .primitive_restart_enable(false);
 
-        let viewport = vk::ViewportBuilder::new()
-            .x(0.0)
-            .y(0.0)
-            .width(render_info.extent.width as f32)
-            .height(render_info.extent.height as f32)
-            .min_depth(0.0)
-            .max_depth(1.0);
-        let scissor = vk::Rect2DBuilder::new()
-            .offset(vk::Offset2D { x: 0, y: 0 })
-            .extent(render_info.extent);
+        // Set when recording, so resizing doesn't need a new pipeline.
         let viewport_state = vk::PipelineViewportStateCreateInfoBuilder::new()
-            .viewports(std::slice::from_ref(&viewport))
-            .scissors(std::slice::from_ref(&scissor));
+            .viewport_count(1)
+            .scissor_count(1);
+        let dynamic_state = vk::PipelineDynamicStateCreateInfoBuilder::new()
+            .dynamic_states(&[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR]);
 
         let rasterization_state = vk::PipelineRasterizationStateCreateInfoBuilder::new()
             .depth_clamp_enable(false)
             .rasterizer_discard_enable(false)
-            .polygon_mode(vk::PolygonMode::LINE)
+            .polygon_mode(if wireframe {
+                vk::PolygonMode::LINE
+            } else {
+                vk::PolygonMode::FILL
+            })
             .cull_mode(vk::CullModeFlags::BACK)
             .front_face(vk::FrontFace::CLOCKWISE)
             .depth_bias_enable(false)
             .line_width(1.0);
 
         let multisample_state = vk::PipelineMultisampleStateCreateInfoBuilder::new()
-            .rasterization_samples(vk::SampleCountFlagBits::_1)
+            .rasterization_samples(render_info.samples)
             .sample_shading_enable(false)
             .alpha_to_coverage_enable(false)
             .alpha_to_one_enable(false);
@@ -185,7 +245,7 @@ impl<T: Instance> VoxelMeshRenderer<T> {
         let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfoBuilder::new()
             .depth_test_enable(true)
             .depth_write_enable(true)
-            .depth_compare_op(vk::CompareOp::LESS)
+            .depth_compare_op(render_info.depth_compare_op())
             .depth_bounds_test_enable(false)
             .stencil_test_enable(false);
 
@@ -205,7 +265,18 @@ impl<T: Instance> VoxelMeshRenderer<T> {
         let layout_create_info = vk::PipelineLayoutCreateInfoBuilder::new()
             .push_constant_ranges(std::slice::from_ref(&push_constant_range));
 
-        let layout = unsafe { device.create_pipeline_layout(&layout_create_info, None) }.unwrap();
+        let layout = unsafe { device.create_pipeline_layout(&layout_create_info, None) }
+            .context("creating a pipeline layout");
+        let layout = match layout {
+            Ok(layout) => layout,
+            Err(err) => {
+                unsafe {
+                    device.destroy_shader_module(Some(vert_shader_module), None);
+                    device.destroy_shader_module(Some(frag_shader_module), None);
+                }
+                return Err(err);
+            }
+        };
 
         let pipeline_create_info = vk::GraphicsPipelineCreateInfoBuilder::new()
             .stages(&stages)
@@ -216,40 +287,69 @@ impl<T: Instance> VoxelMeshRenderer<T> {
             .multisample_state(&multisample_state)
             .depth_stencil_state(&depth_stencil_state)
             .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state)
             .layout(layout)
             .render_pass(render_info.render_pass)
             .subpass(0);
 
-        let pipeline =
-            unsafe { device.create_graphics_pipelines(None, &[pipeline_create_info], None) }
-                .unwrap()[0];
+        let pipeline = unsafe {
+            device.create_graphics_pipelines(
+                Some(instance.pipeline_cache()),
+                &[pipeline_create_info],
+                None,
+            )
+        }
+        .context("creating a graphics pipeline");
 
         unsafe {
             device.destroy_shader_module(Some(vert_shader_module), None);
             device.destroy_shader_module(Some(frag_shader_module), None);
         }
 
-        Self {
+        let pipeline = match pipeline {
+            Ok(pipelines) => pipelines[0],
+            Err(err) => {
+                unsafe { device.destroy_pipeline_layout(Some(layout), None) };
+                return Err(err);
+            }
+        };
+
+        Ok(Self {
             instance,
             layout,
             pipeline,
-            extent: render_info.extent,
-            v_fov: 45.0,
+            v_fov: Self::DEFAULT_V_FOV,
+            wireframe,
+            shaders,
+        })
+    }
+
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    /// Switches between wireframe and filled faces. The pipeline is rebuilt, so it must not be
+    /// in use.
+    pub fn set_wireframe(&mut self, wireframe: bool, surface: &impl Renderable) -> Result<()> {
+        let old_wireframe = std::mem::replace(&mut self.wireframe, wireframe);
+        let rebuilt = self.rebuild(surface, self.shaders.clone());
+        if rebuilt.is_err() {
+            self.wireframe = old_wireframe
         }
+        rebuilt
     }
 
-    fn perspective_mat(&self) -> uv::Mat4 {
-        uv::projection::perspective_infinite_z_vk(
-            self.v_fov,
-            self.extent.width as f32 / self.extent.height as f32,
-            0.1,
-        )
+    /// Sets the vertical field of view, in degrees.
+    pub fn set_v_fov(&mut self, v_fov: f32) {
+        self.v_fov = v_fov.to_radians()
     }
 
-    pub fn render(
+    /// Draws the meshes of each manager, placed by the model matrix paired with it.
+    pub fn render<'a, I: Instance + 'a>(
         &mut self,
         command_buffer: vk::CommandBuffer,
-        manager: &VoxelMeshManager<impl Instance>,
+        render_info: RenderInfo,
+        objects: impl IntoIterator<Item = (&'a VoxelMeshManager<I>, uv::Mat4)>,
         camera: &crate::camera::Camera,
     ) {
         let device = self.instance.device();
@@ -260,31 +360,49 @@ impl<T: Instance> VoxelMeshRenderer<T> {
                 self.pipeline,
             );
         }
-        let projection_mat = self.perspective_mat() * camera.look_mat();
-        for mesh in manager.meshes() {
-            let transform_mat = projection_mat * mesh.mat;
-            unsafe {
-                device.cmd_push_constants(
-                    command_buffer,
-                    self.layout,
-                    vk::ShaderStageFlags::VERTEX,
-                    0,
-                    std::mem::size_of::<uv::Mat4>() as u32,
-                    transform_mat.as_ptr().cast(),
-                );
-                device.cmd_bind_vertex_buffers(command_buffer, 0, &[mesh.vertex_buffer], &[0]);
-                device.cmd_draw(command_buffer, 6, mesh.length, 0, 0)
+        let projection_mat = render_info.perspective_mat(self.v_fov) * camera.look_mat();
+        for (manager, model_mat) in objects {
+            let object_mat = projection_mat * model_mat;
+            for mesh in manager.meshes() {
+                let transform_mat = object_mat * mesh.mat;
+                unsafe {
+                    device.cmd_push_constants(
+                        command_buffer,
+                        self.layout,
+                        vk::ShaderStageFlags::VERTEX,
+                        0,
+                        std::mem::size_of::<uv::Mat4>() as u32,
+                        transform_mat.as_ptr().cast(),
+                    );
+                    device.cmd_bind_vertex_buffers(command_buffer, 0, &[mesh.vertex_buffer], &[0]);
+                    device.cmd_draw(command_buffer, 6, mesh.length, 0, 0)
+                }
             }
         }
     }
 
-    pub fn rebuild(&mut self, surface: &impl Renderable) {
-        let instance = self.instance.clone();
-        unsafe {
-            std::mem::drop(std::ptr::read(self));
-            std::ptr::write(self, Self::new(instance, surface))
+    /// Replaces the shader with file name `name`, e.g. `voxel.frag`, and rebuilds the pipeline,
+    /// which must not be in use. Returns `false` if this renderer doesn't use that shader. On
+    /// failure the old shader and pipeline stay in use.
+    pub fn set_shader(
+        &mut self,
+        name: &str,
+        spirv: &[u8],
+        surface: &impl Renderable,
+    ) -> Result<bool> {
+        match self.shaders.replaced(name, spirv) {
+            Some(shaders) => self.rebuild(surface, shaders).map(|()| true),
+            None => Ok(false),
         }
     }
+
+    /// Recreates the pipeline for `surface`. On failure the old pipeline is kept.
+    fn rebuild(&mut self, surface: &impl Renderable, shaders: ShaderPair) -> Result<()> {
+        let v_fov = self.v_fov;
+        *self = Self::create(self.instance.clone(), surface, self.wireframe, shaders)?;
+        self.v_fov = v_fov;
+        Ok(())
+    }
 }
 
 impl<T: Instance> Drop for VoxelMeshRenderer<T> {
@@ -0,0 +1,171 @@
+use super::{Object, Voxel, VoxelBox, VoxelCoord};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BrushMode {
+    /// Fill the shape, overwriting whatever is there.
+    Add,
+    /// Clear the shape to void.
+    Subtract,
+    /// Recolor solid voxels in the shape, leaving void alone.
+    Paint,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BrushShape {
+    Sphere {
+        center: uv::Vec3,
+        radius: f32,
+    },
+    Box(VoxelBox),
+    /// Cylinder between the centers of its two caps.
+    Cylinder {
+        base: uv::Vec3,
+        top: uv::Vec3,
+        radius: f32,
+    },
+    /// Cone from the center of its base disc to its apex.
+    Cone {
+        base: uv::Vec3,
+        apex: uv::Vec3,
+        radius: f32,
+    },
+    /// One voxel thick 3D Bresenham line.
+    Line {
+        start: VoxelCoord,
+        end: VoxelCoord,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Brush {
+    pub shape: BrushShape,
+    pub mode: BrushMode,
+    pub voxel: Voxel,
+}
+
+impl BrushMode {
+    pub fn apply(&self, old: Voxel, voxel: Voxel) -> Voxel {
+        match self {
+            BrushMode::Add => voxel,
+            BrushMode::Subtract => Voxel::VOID,
+            BrushMode::Paint if old.is_void() => old,
+            BrushMode::Paint => voxel,
+        }
+    }
+}
+
+impl BrushShape {
+    pub fn bounds(&self) -> VoxelBox {
+        match *self {
+            BrushShape::Sphere { center, radius } => float_bounds(
+                center - uv::Vec3::broadcast(radius),
+                center + uv::Vec3::broadcast(radius),
+            ),
+            BrushShape::Box(bounds) => bounds,
+            BrushShape::Cylinder { base, top, radius } => float_bounds(
+                base.min_by_component(top) - uv::Vec3::broadcast(radius),
+                base.max_by_component(top) + uv::Vec3::broadcast(radius),
+            ),
+            BrushShape::Cone { base, apex, radius } => float_bounds(
+                base.min_by_component(apex) - uv::Vec3::broadcast(radius),
+                base.max_by_component(apex) + uv::Vec3::broadcast(radius),
+            ),
+            BrushShape::Line { start, end } => VoxelBox::new(start, end),
+        }
+    }
+
+    pub fn contains(&self, coord: VoxelCoord) -> bool {
+        let pos = coord.pos();
+        match *self {
+            BrushShape::Sphere { center, radius } => (pos - center).mag_sq() <= radius * radius,
+            BrushShape::Box(bounds) => bounds.contains(coord),
+            BrushShape::Cylinder { base, top, radius } => match along_axis(pos, base, top) {
+                Some((t, dist_sq)) => (0.0..=1.0).contains(&t) && dist_sq <= radius * radius,
+                None => false,
+            },
+            BrushShape::Cone { base, apex, radius } => match along_axis(pos, base, apex) {
+                Some((t, dist_sq)) => {
+                    let radius = radius * (1.0 - t);
+                    (0.0..=1.0).contains(&t) && dist_sq <= radius * radius
+                }
+                None => false,
+            },
+            BrushShape::Line { start, end } => line(start, end).contains(&coord),
+        }
+    }
+}
+
+impl Brush {
+    pub fn new(shape: BrushShape, mode: BrushMode, voxel: Voxel) -> Self {
+        Self { shape, mode, voxel }
+    }
+
+    /// Applies one stroke of the brush. Each chunk the shape overlaps is looked up once.
+    pub fn apply(&self, object: &mut Object) {
+        let (shape, mode, voxel) = (self.shape, self.mode, self.voxel);
+        match shape {
+            BrushShape::Line { start, end } => {
+                object.map_voxels(line(start, end), |_, old| mode.apply(old, voxel))
+            }
+            _ => object.map_box(shape.bounds(), |coord, old| {
+                if shape.contains(coord) {
+                    mode.apply(old, voxel)
+                } else {
+                    old
+                }
+            }),
+        }
+    }
+}
+
+/// Voxels on the 3D Bresenham line from `start` to `end`, both included.
+pub fn line(start: VoxelCoord, end: VoxelCoord) -> Vec<VoxelCoord> {
+    let delta = end.vec - start.vec;
+    let step = delta.map(i32::signum);
+    let abs = delta.map(i32::abs);
+    let steps = abs.component_max();
+
+    // Drive along the longest axis, accumulating error on the other two.
+    let mut coord = start.vec;
+    let mut err = uv::IVec3::broadcast(steps) / 2;
+    let mut voxels = Vec::with_capacity(steps as usize + 1);
+    voxels.push(start);
+    for _ in 0..steps {
+        err -= abs;
+        for axis in 0..3 {
+            if err[axis] < 0 {
+                err[axis] += steps;
+                coord[axis] += step[axis]
+            }
+        }
+        voxels.push(VoxelCoord { vec: coord })
+    }
+    voxels
+}
+
+/// Position of `pos` along the axis from `a` to `b` (0 at `a`, 1 at `b`) and its squared
+/// distance from the axis. `None` if the axis is degenerate.
+fn along_axis(pos: uv::Vec3, a: uv::Vec3, b: uv::Vec3) -> Option<(f32, f32)> {
+    let axis = b - a;
+    let len_sq = axis.mag_sq();
+    if len_sq == 0.0 {
+        return None;
+    }
+    let t = (pos - a).dot(axis) / len_sq;
+    Some((t, (pos - (a + axis * t)).mag_sq()))
+}
+
+fn float_bounds(min: uv::Vec3, max: uv::Vec3) -> VoxelBox {
+    VoxelBox::new(
+        VoxelCoord::new(
+            min.x.floor() as i32,
+            min.y.floor() as i32,
+            min.z.floor() as i32,
+        ),
+        VoxelCoord::new(
+            max.x.ceil() as i32,
+            max.y.ceil() as i32,
+            max.z.ceil() as i32,
+        ),
+    )
+}
@@ -0,0 +1,208 @@
+use super::{Chunk, ChunkCoord, ChunkIndex, Direction, Object, VoxelBox, VoxelCoord};
+
+/// Labelling of the 6-connected components of an object's solid voxels.
+#[derive(Debug)]
+pub struct Components {
+    /// Per chunk, the component of each voxel plus one, or 0 for void.
+    labels: std::collections::HashMap<ChunkCoord, Vec<u32>>,
+    bounds: Vec<VoxelBox>,
+    sizes: Vec<usize>,
+}
+
+impl Components {
+    pub fn label(object: &Object) -> Self {
+        let mut components = Self {
+            labels: std::collections::HashMap::new(),
+            bounds: Vec::new(),
+            sizes: Vec::new(),
+        };
+        let mut queue = std::collections::VecDeque::new();
+        for (chunk_coord, chunk) in object.chunks() {
+            for index in ChunkIndex::iterate() {
+                let start = VoxelCoord::from_chunk(chunk_coord, index);
+                if chunk[index].is_void() || components.component(start).is_some() {
+                    continue;
+                }
+
+                let label = components.sizes.len() as u32 + 1;
+                components.bounds.push(VoxelBox::new(start, start));
+                components.sizes.push(0);
+                components.set_label(start, label);
+                queue.push_back(start);
+                while let Some(coord) = queue.pop_front() {
+                    let component = label as usize - 1;
+                    let bounds = &mut components.bounds[component];
+                    *bounds = VoxelBox::new(
+                        VoxelCoord {
+                            vec: bounds.min.vec.min_by_component(coord.vec),
+                        },
+                        VoxelCoord {
+                            vec: bounds.max.vec.max_by_component(coord.vec),
+                        },
+                    );
+                    components.sizes[component] += 1;
+                    for direction in Direction::iterate() {
+                        let neighbor = coord.advance(direction);
+                        if !object[neighbor].is_void() && components.component(neighbor).is_none() {
+                            components.set_label(neighbor, label);
+                            queue.push_back(neighbor)
+                        }
+                    }
+                }
+            }
+        }
+        components
+    }
+
+    pub fn count(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// Component `coord` belongs to, or `None` if it's void.
+    pub fn component(&self, coord: VoxelCoord) -> Option<usize> {
+        let label = self.labels.get(&coord.chunk())?[coord.chunk_index().0 as usize];
+        (label != 0).then(|| label as usize - 1)
+    }
+
+    /// Number of voxels in `component`.
+    pub fn size(&self, component: usize) -> usize {
+        self.sizes[component]
+    }
+
+    /// Smallest box containing `component`.
+    pub fn bounds(&self, component: usize) -> VoxelBox {
+        self.bounds[component]
+    }
+
+    fn set_label(&mut self, coord: VoxelCoord, label: u32) {
+        self.labels
+            .entry(coord.chunk())
+            .or_insert_with(|| vec![0; Chunk::VOLUME as usize])[coord.chunk_index().0 as usize] =
+            label
+    }
+}
+
+impl Object {
+    pub fn components(&self) -> Components {
+        Components::label(self)
+    }
+
+    /// Splits the object into one object per connected component. Each comes with its origin:
+    /// the minimum corner of the component, where its own `VoxelCoord::new(0, 0, 0)` sits in
+    /// this object.
+    pub fn split(&self) -> Vec<(VoxelCoord, Object)> {
+        let components = self.components();
+        let mut objects = (0..components.count())
+            .map(|component| (components.bounds(component).min, Object::new()))
+            .collect::<Vec<_>>();
+        for (chunk_coord, chunk) in self.chunks() {
+            for index in ChunkIndex::iterate() {
+                let coord = VoxelCoord::from_chunk(chunk_coord, index);
+                if let Some(component) = components.component(coord) {
+                    let (origin, object) = &mut objects[component];
+                    object[VoxelCoord {
+                        vec: coord.vec - origin.vec,
+                    }] = chunk[index]
+                }
+            }
+        }
+        objects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::Voxel;
+
+    fn fill(object: &mut Object, a: VoxelCoord, b: VoxelCoord) {
+        object.map_box(VoxelBox::new(a, b), |_, _| Voxel::from_id(1))
+    }
+
+    fn solid_count(object: &Object) -> usize {
+        object
+            .chunks()
+            .map(|(_, chunk)| {
+                ChunkIndex::iterate()
+                    .filter(|index| !chunk[*index].is_void())
+                    .count()
+            })
+            .sum()
+    }
+
+    /// Two bars crossing several chunk boundaries, joined by a bridge at x = 0.
+    fn bridge() -> Object {
+        let mut object = Object::new();
+        fill(
+            &mut object,
+            VoxelCoord::new(-40, -2, -2),
+            VoxelCoord::new(40, 2, 2),
+        );
+        fill(
+            &mut object,
+            VoxelCoord::new(-40, 10, -2),
+            VoxelCoord::new(40, 14, 2),
+        );
+        fill(
+            &mut object,
+            VoxelCoord::new(0, 3, 0),
+            VoxelCoord::new(0, 9, 0),
+        );
+        object
+    }
+
+    #[test]
+    fn bridged_bars_are_one_component() {
+        let components = bridge().components();
+        assert_eq!(components.count(), 1);
+        assert_eq!(components.size(0), 2 * 81 * 25 + 7);
+        assert_eq!(
+            components.bounds(0),
+            VoxelBox::new(VoxelCoord::new(-40, -2, -2), VoxelCoord::new(40, 14, 2))
+        );
+    }
+
+    #[test]
+    fn cut_bridge_splits_in_two() {
+        let mut object = bridge();
+        object[VoxelCoord::new(0, 6, 0)] = Voxel::VOID;
+        let components = object.components();
+        assert_eq!(components.count(), 2);
+        let low = components.component(VoxelCoord::new(-40, 0, 0)).unwrap();
+        let high = components.component(VoxelCoord::new(40, 12, 0)).unwrap();
+        assert_ne!(low, high);
+        assert_eq!(components.component(VoxelCoord::new(0, 5, 0)), Some(low));
+        assert_eq!(components.component(VoxelCoord::new(0, 7, 0)), Some(high));
+        assert_eq!(components.component(VoxelCoord::new(0, 6, 0)), None);
+    }
+
+    #[test]
+    fn diagonal_neighbors_are_not_connected() {
+        let mut object = Object::new();
+        object[VoxelCoord::new(31, 31, 31)] = Voxel::from_id(1);
+        object[VoxelCoord::new(32, 32, 32)] = Voxel::from_id(1);
+        assert_eq!(object.components().count(), 2);
+    }
+
+    #[test]
+    fn split_keeps_voxels_relative_to_origin() {
+        let mut object = bridge();
+        object[VoxelCoord::new(0, 6, 0)] = Voxel::VOID;
+        object[VoxelCoord::new(-40, 0, 0)] = Voxel::from_id(2);
+        let mut pieces = object.split();
+        assert_eq!(pieces.len(), 2);
+        pieces.sort_by_key(|(origin, _)| origin.vec.y);
+
+        let (origin, low) = &pieces[0];
+        assert_eq!(*origin, VoxelCoord::new(-40, -2, -2));
+        assert_eq!(solid_count(low), 81 * 25 + 3);
+        assert_eq!(low[VoxelCoord::new(0, 2, 2)], Voxel::from_id(2));
+        assert_eq!(low[VoxelCoord::new(40, 7, 2)], Voxel::from_id(1));
+
+        let (origin, high) = &pieces[1];
+        assert_eq!(*origin, VoxelCoord::new(-40, 7, -2));
+        assert_eq!(solid_count(high), 81 * 25 + 3);
+        assert!(high[VoxelCoord::new(0, 0, 0)].is_void());
+        assert_eq!(high[VoxelCoord::new(40, 0, 2)], Voxel::from_id(1));
+    }
+}
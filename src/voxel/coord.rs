@@ -13,6 +13,13 @@ pub enum Direction {
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Axis {
+    X = 0,
+    Y = 1,
+    Z = 2,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub struct VoxelCoord {
     pub vec: uv::IVec3,
 }
@@ -25,6 +32,13 @@ pub struct ChunkCoord {
 #[derive(Copy, Clone, Eq, PartialEq)]
 pub struct ChunkIndex(pub u32);
 
+/// Axis-aligned box of voxels, inclusive on both ends.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct VoxelBox {
+    pub min: VoxelCoord,
+    pub max: VoxelCoord,
+}
+
 impl Direction {
     pub const NORTH: Self = Self::PosY;
     pub const EAST: Self = Self::PosX;
@@ -66,6 +80,15 @@ impl VoxelCoord {
         }
     }
 
+    /// Voxel whose cube contains `pos`.
+    pub fn containing(pos: uv::Vec3) -> Self {
+        Self::new(
+            (pos.x + 0.5).floor() as i32,
+            (pos.y + 0.5).floor() as i32,
+            (pos.z + 0.5).floor() as i32,
+        )
+    }
+
     pub fn advance(&self, direction: Direction) -> Self {
         Self {
             vec: self.vec + direction.vec(),
@@ -80,6 +103,17 @@ impl VoxelCoord {
         )
     }
 
+    pub fn from_chunk(chunk: ChunkCoord, index: ChunkIndex) -> Self {
+        Self {
+            vec: chunk.origin().vec
+                + uv::IVec3::new(index.x() as i32, index.y() as i32, index.z() as i32),
+        }
+    }
+
+    pub fn pos(&self) -> uv::Vec3 {
+        uv::Vec3::new(self.vec.x as f32, self.vec.y as f32, self.vec.z as f32)
+    }
+
     pub(in crate::voxel) fn chunk_index(&self) -> ChunkIndex {
         ChunkIndex::new_unchecked(
             (self.vec.x & Chunk::BIT_MASK as i32) as u32,
@@ -102,6 +136,24 @@ impl ChunkCoord {
         }
     }
 
+    pub fn origin(&self) -> VoxelCoord {
+        VoxelCoord::new(
+            self.vec.x << Chunk::BITS,
+            self.vec.y << Chunk::BITS,
+            self.vec.z << Chunk::BITS,
+        )
+    }
+
+    pub fn voxel_box(&self) -> VoxelBox {
+        let origin = self.origin();
+        VoxelBox {
+            min: origin,
+            max: VoxelCoord {
+                vec: origin.vec + uv::IVec3::broadcast(Chunk::BIT_MASK as i32),
+            },
+        }
+    }
+
     pub fn mat(&self) -> uv::Mat4 {
         uv::Mat4::from_translation(uv::Vec3::new(
             (self.vec.x << Chunk::BITS) as f32,
@@ -139,6 +191,85 @@ impl ChunkIndex {
     }
 }
 
+impl VoxelBox {
+    pub fn new(a: VoxelCoord, b: VoxelCoord) -> Self {
+        Self {
+            min: VoxelCoord {
+                vec: a.vec.min_by_component(b.vec),
+            },
+            max: VoxelCoord {
+                vec: a.vec.max_by_component(b.vec),
+            },
+        }
+    }
+
+    pub fn size(&self) -> uv::IVec3 {
+        self.max.vec - self.min.vec + uv::IVec3::one()
+    }
+
+    pub fn volume(&self) -> usize {
+        let size = self.size();
+        size.x as usize * size.y as usize * size.z as usize
+    }
+
+    pub fn contains(&self, coord: VoxelCoord) -> bool {
+        coord.vec.x >= self.min.vec.x
+            && coord.vec.y >= self.min.vec.y
+            && coord.vec.z >= self.min.vec.z
+            && coord.vec.x <= self.max.vec.x
+            && coord.vec.y <= self.max.vec.y
+            && coord.vec.z <= self.max.vec.z
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let min = self.min.vec.max_by_component(other.min.vec);
+        let max = self.max.vec.min_by_component(other.max.vec);
+        if min.x <= max.x && min.y <= max.y && min.z <= max.z {
+            Some(Self {
+                min: VoxelCoord { vec: min },
+                max: VoxelCoord { vec: max },
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn expanded(&self, amount: i32) -> Self {
+        Self {
+            min: VoxelCoord {
+                vec: self.min.vec - uv::IVec3::broadcast(amount),
+            },
+            max: VoxelCoord {
+                vec: self.max.vec + uv::IVec3::broadcast(amount),
+            },
+        }
+    }
+
+    /// Position of `coord` in [`VoxelBox::iterate`] order. `coord` must be in the box.
+    pub fn index_of(&self, coord: VoxelCoord) -> usize {
+        let (rel, size) = (coord.vec - self.min.vec, self.size());
+        (rel.x + size.x * (rel.y + size.y * rel.z)) as usize
+    }
+
+    /// Chunks overlapping the box.
+    pub fn chunks(&self) -> impl Iterator<Item = ChunkCoord> {
+        let (min, max) = (self.min.chunk().vec, self.max.chunk().vec);
+        (min.z..=max.z).flat_map(move |z| {
+            (min.y..=max.y)
+                .flat_map(move |y| (min.x..=max.x).map(move |x| ChunkCoord::new(x, y, z)))
+        })
+    }
+
+    /// Voxels in the box, x varying fastest.
+    pub fn iterate(&self) -> impl Iterator<Item = VoxelCoord> {
+        let (min, max) = (self.min.vec, self.max.vec);
+        (min.z..=max.z).flat_map(move |z| {
+            (min.y..=max.y)
+                .flat_map(move |y| (min.x..=max.x).map(move |x| VoxelCoord::new(x, y, z)))
+        })
+    }
+}
+
 impl std::fmt::Debug for ChunkIndex {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ChunkIndex")
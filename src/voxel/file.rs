@@ -0,0 +1,286 @@
+use super::{Chunk, ChunkCoord, ChunkIndex, Object, Voxel, VoxelCoord};
+use std::convert::TryFrom;
+use std::io::{Read, Write};
+
+/// How an [`Object`] is stored on disk.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WorldFormat {
+    /// Compact binary format. After the magic bytes and a version, there is a chunk count,
+    /// then each chunk's coordinate followed by runs of `(length, voxel id)` covering it in
+    /// [`ChunkIndex`] order. Every number is a little endian 32 bit integer.
+    Binary,
+    /// Plain text, one solid voxel per line: `x y z id`. Easy to diff and to produce from
+    /// other tools, but large.
+    Text,
+}
+
+impl WorldFormat {
+    const MAGIC: &'static [u8; 8] = b"STANNOX\0";
+    const VERSION: u32 = 1;
+
+    /// Text for `.txt` files, binary for anything else.
+    pub fn from_path(path: &std::path::Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("txt") => Self::Text,
+            _ => Self::Binary,
+        }
+    }
+}
+
+impl Object {
+    /// Loads a world in the format picked by [`WorldFormat::from_path`].
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        match WorldFormat::from_path(path) {
+            WorldFormat::Binary => Self::read_binary(reader),
+            WorldFormat::Text => Self::read_text(reader),
+        }
+    }
+
+    /// Saves the world in the format picked by [`WorldFormat::from_path`].
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let path = path.as_ref();
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        match WorldFormat::from_path(path) {
+            WorldFormat::Binary => self.write_binary(&mut writer)?,
+            WorldFormat::Text => self.write_text(&mut writer)?,
+        }
+        writer.flush()
+    }
+
+    pub fn read_binary(mut reader: impl Read) -> std::io::Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != WorldFormat::MAGIC {
+            return Err(invalid_data("not a stannox world"));
+        }
+        let version = read_u32(&mut reader)?;
+        if version != WorldFormat::VERSION {
+            return Err(invalid_data(format!("unsupported version {}", version)));
+        }
+
+        let mut object = Self::new();
+        for _ in 0..read_u32(&mut reader)? {
+            let coord = ChunkCoord::new(
+                read_u32(&mut reader)? as i32,
+                read_u32(&mut reader)? as i32,
+                read_u32(&mut reader)? as i32,
+            );
+            let mut chunk = Chunk::new_void();
+            let mut index = 0;
+            while index < Chunk::VOLUME {
+                let length = read_u32(&mut reader)?;
+                let voxel = Voxel(read_u32(&mut reader)?);
+                if length == 0 || length > Chunk::VOLUME - index {
+                    return Err(invalid_data(format!("bad run length in chunk {:?}", coord)));
+                }
+                for index in index..index + length {
+                    chunk[ChunkIndex(index)] = voxel
+                }
+                index += length
+            }
+            if object.insert_chunk(coord, chunk).is_some() {
+                return Err(invalid_data(format!("chunk {:?} appears twice", coord)));
+            }
+        }
+        Ok(object)
+    }
+
+    pub fn write_binary(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(WorldFormat::MAGIC)?;
+        writer.write_all(&WorldFormat::VERSION.to_le_bytes())?;
+        writer.write_all(&(self.chunk_count() as u32).to_le_bytes())?;
+        for (coord, chunk) in self.chunks() {
+            for component in &[coord.vec.x, coord.vec.y, coord.vec.z] {
+                writer.write_all(&component.to_le_bytes())?;
+            }
+            let mut run = (0u32, chunk[ChunkIndex(0)]);
+            for index in ChunkIndex::iterate() {
+                if chunk[index] == run.1 {
+                    run.0 += 1
+                } else {
+                    write_run(&mut writer, run)?;
+                    run = (1, chunk[index])
+                }
+            }
+            write_run(&mut writer, run)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_text(reader: impl std::io::BufRead) -> std::io::Result<Self> {
+        let mut object = Self::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let invalid = || invalid_data(format!("bad voxel on line {}: {:?}", number + 1, line));
+            let values = line
+                .split_whitespace()
+                .map(str::parse::<i64>)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| invalid())?;
+            match values[..] {
+                [x, y, z, id] => {
+                    let coord = VoxelCoord::new(
+                        i32::try_from(x).map_err(|_| invalid())?,
+                        i32::try_from(y).map_err(|_| invalid())?,
+                        i32::try_from(z).map_err(|_| invalid())?,
+                    );
+                    let voxel = Voxel(u32::try_from(id).map_err(|_| invalid())?);
+                    if !voxel.is_void() {
+                        object[coord] = voxel
+                    }
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(object)
+    }
+
+    pub fn write_text(&self, mut writer: impl Write) -> std::io::Result<()> {
+        for (coord, voxel) in self.solid_voxels() {
+            writeln!(
+                writer,
+                "{} {} {} {}",
+                coord.vec.x, coord.vec.y, coord.vec.z, voxel.0
+            )?
+        }
+        Ok(())
+    }
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn write_run(writer: &mut impl Write, (length, voxel): (u32, Voxel)) -> std::io::Result<()> {
+    writer.write_all(&length.to_le_bytes())?;
+    writer.write_all(&voxel.0.to_le_bytes())
+}
+
+fn invalid_data(message: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::voxel::VoxelBox;
+
+    /// Runs of a few materials with gaps, spanning the boundaries of eight chunks.
+    fn striped() -> Object {
+        let mut object = Object::new();
+        object.map_box(
+            VoxelBox::new(VoxelCoord::new(-5, -3, -2), VoxelCoord::new(37, 4, 1)),
+            |coord, _| match coord.vec.x.rem_euclid(7) {
+                0 => Voxel::VOID,
+                1..=3 => Voxel::from_id(1),
+                _ => Voxel::from_id(2 + coord.vec.y.rem_euclid(2) as u32),
+            },
+        );
+        object[VoxelCoord::new(100, 100, 100)] = Voxel::from_id(u32::MAX);
+        object
+    }
+
+    fn sorted_voxels(object: &Object) -> Vec<((i32, i32, i32), u32)> {
+        let mut voxels = object
+            .solid_voxels()
+            .map(|(coord, voxel)| ((coord.vec.x, coord.vec.y, coord.vec.z), voxel.0))
+            .collect::<Vec<_>>();
+        voxels.sort_unstable();
+        voxels
+    }
+
+    fn binary(object: &Object) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        object.write_binary(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn binary_round_trips() {
+        let object = striped();
+        let loaded = Object::read_binary(&binary(&object)[..]).unwrap();
+        assert_eq!(loaded.chunk_count(), object.chunk_count());
+        assert_eq!(sorted_voxels(&loaded), sorted_voxels(&object));
+    }
+
+    #[test]
+    fn text_round_trips() {
+        let object = striped();
+        let mut text = Vec::new();
+        object.write_text(&mut text).unwrap();
+        let loaded = Object::read_text(&text[..]).unwrap();
+        assert_eq!(sorted_voxels(&loaded), sorted_voxels(&object));
+    }
+
+    #[test]
+    fn truncated_binary_is_an_error() {
+        let bytes = binary(&striped());
+        for len in [
+            0,
+            4,
+            8,
+            12,
+            16,
+            20,
+            27,
+            28,
+            36,
+            bytes.len() / 2,
+            bytes.len() - 1,
+        ] {
+            assert!(
+                Object::read_binary(&bytes[..len]).is_err(),
+                "length {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn corrupted_binary_is_an_error() {
+        let bytes = binary(&striped());
+        let corrupt = |offset: usize, value: u32| {
+            let mut bytes = bytes.clone();
+            bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            Object::read_binary(&bytes[..])
+        };
+        // Magic, version, the first run's length, and a run past the end of the chunk.
+        assert!(corrupt(0, 0).is_err());
+        assert!(corrupt(8, WorldFormat::VERSION + 1).is_err());
+        assert!(corrupt(28, 0).is_err());
+        assert!(corrupt(28, Chunk::VOLUME + 1).is_err());
+        // More chunks than there are.
+        assert!(corrupt(12, u32::MAX).is_err());
+
+        let mut twice = binary(&Object::new());
+        twice[12..16].copy_from_slice(&2u32.to_le_bytes());
+        for _ in 0..2 {
+            twice.extend_from_slice(&[0; 12]);
+            twice.extend_from_slice(&Chunk::VOLUME.to_le_bytes());
+            twice.extend_from_slice(&1u32.to_le_bytes());
+        }
+        assert!(Object::read_binary(&twice[..]).is_err());
+    }
+
+    #[test]
+    fn bad_text_is_an_error() {
+        for text in [
+            "1 2 3",
+            "1 2 3 4 5",
+            "1 2 three 4",
+            "1 2 3 -4",
+            "1 2 3 4294967296",
+            "2147483648 0 0 1",
+        ] {
+            assert!(Object::read_text(text.as_bytes()).is_err(), "{:?}", text);
+        }
+        assert!(Object::read_text("\n1 2 3 4\n\n".as_bytes()).is_ok());
+    }
+}
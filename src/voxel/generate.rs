@@ -0,0 +1,98 @@
+use super::{sdf, Object, Voxel, VoxelBox, VoxelCoord};
+
+/// Procedural world generators, for `stannox new`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Generator {
+    /// The row of spheres the client starts with. Ignores the seed.
+    Test,
+    /// A flat slab of ground.
+    Flat,
+    /// Rolling terrain from value noise.
+    Hills,
+}
+
+impl Generator {
+    pub const ALL: [Self; 3] = [Self::Test, Self::Flat, Self::Hills];
+
+    /// Side length in voxels of the square of ground `Flat` and `Hills` cover.
+    const SIZE: i32 = 256;
+    const GROUND: Voxel = Voxel(1);
+    const SURFACE: Voxel = Voxel(2);
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Test => "test",
+            Self::Flat => "flat",
+            Self::Hills => "hills",
+        }
+    }
+
+    pub fn generate(&self, seed: u64) -> Object {
+        match self {
+            Self::Test => Object::new_test(),
+            Self::Flat => Self::terrain(|_, _| 4),
+            Self::Hills => {
+                // The noise takes 32 bit seeds, so the top half is folded in rather than dropped.
+                let seed = (seed ^ (seed >> 32)) as u32;
+                let noise = |seed: u32, spacing: f32, x: i32, y: i32| {
+                    let pos = uv::Vec3::new(x as f32, y as f32, 0.0) / spacing;
+                    sdf::noise(pos, seed) * 0.5 + 0.5
+                };
+                Self::terrain(|x, y| {
+                    // Two octaves: broad hills with smaller bumps on top.
+                    let height = 24.0 * noise(seed, 64.0, x, y)
+                        + 6.0 * noise(seed.wrapping_add(1), 16.0, x, y);
+                    4 + height as i32
+                })
+            }
+        }
+    }
+
+    /// Columns of ground over the generated square, each `height(x, y)` voxels tall with a
+    /// surface voxel on top.
+    fn terrain(height: impl Fn(i32, i32) -> i32) -> Object {
+        let heights = (0..Self::SIZE)
+            .flat_map(|y| (0..Self::SIZE).map(move |x| (x, y)))
+            .map(|(x, y)| height(x, y).max(1))
+            .collect::<Vec<_>>();
+        let top = heights.iter().copied().max().unwrap_or(1);
+
+        let mut object = Object::new();
+        object.map_box(
+            VoxelBox::new(
+                VoxelCoord::new(0, 0, 0),
+                VoxelCoord::new(Self::SIZE - 1, Self::SIZE - 1, top - 1),
+            ),
+            |coord, _| {
+                let height = heights[(coord.vec.y * Self::SIZE + coord.vec.x) as usize];
+                if coord.vec.z == height - 1 {
+                    Self::SURFACE
+                } else if coord.vec.z < height {
+                    Self::GROUND
+                } else {
+                    Voxel::VOID
+                }
+            },
+        );
+        object
+    }
+}
+
+impl std::str::FromStr for Generator {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|generator| generator.name() == name)
+            .ok_or_else(|| {
+                let names = Self::ALL.iter().map(Self::name).collect::<Vec<_>>();
+                format!(
+                    "unknown generator {:?}, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
@@ -1,13 +1,26 @@
+pub use brush::{Brush, BrushMode, BrushShape};
 pub use chunk::Chunk;
 use coord::ChunkIndex;
-pub use coord::{ChunkCoord, Direction, VoxelCoord};
+pub use coord::{Axis, ChunkCoord, Direction, VoxelBox, VoxelCoord};
+pub use generate::Generator;
 pub use mesh::{Mesh, MeshFace};
 pub use object::Object;
+pub use raycast::RaycastHit;
+pub use region::VoxelRegion;
 
+mod brush;
 mod chunk;
+mod components;
 mod coord;
+mod file;
+mod generate;
 mod mesh;
 mod object;
+mod raycast;
+mod region;
+pub mod sculpt;
+pub mod sdf;
+mod stats;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct Voxel(pub(in crate::voxel) u32);
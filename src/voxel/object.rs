@@ -1,4 +1,4 @@
-use super::{Chunk, ChunkCoord, Direction, Mesh, Voxel, VoxelCoord};
+use super::{Chunk, ChunkCoord, ChunkIndex, Direction, Mesh, Voxel, VoxelBox, VoxelCoord};
 
 pub struct Object {
     chunks: std::collections::HashMap<ChunkCoord, Chunk>,
@@ -30,6 +30,10 @@ impl Object {
         meshes
     }
 
+    pub fn mesh(&self, coord: ChunkCoord) -> Option<Mesh> {
+        crate::voxel::mesh::mesh(self, coord)
+    }
+
     pub fn neighbors(&self, coord: ChunkCoord) -> [(Direction, Option<&Chunk>); 6] {
         [
             (Direction::PosX, self.chunk(coord.advance(Direction::PosX))),
@@ -41,12 +45,160 @@ impl Object {
         ]
     }
 
+    pub fn chunks(&self) -> impl Iterator<Item = (ChunkCoord, &Chunk)> {
+        self.chunks.iter().map(|(coord, chunk)| (*coord, chunk))
+    }
+
     pub fn chunk(&self, coord: ChunkCoord) -> Option<&Chunk> {
         self.chunks.get(&coord)
     }
 
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Puts `chunk` at `coord`, returning the chunk that was there.
+    pub fn insert_chunk(&mut self, coord: ChunkCoord, chunk: Chunk) -> Option<Chunk> {
+        self.chunks.insert(coord, chunk)
+    }
+
     pub fn chunk_mut(&mut self, coord: ChunkCoord) -> &mut Chunk {
-        self.chunks.entry(coord).or_insert(Chunk::new_void())
+        self.chunks.entry(coord).or_insert_with(Chunk::new_void)
+    }
+
+    /// Every solid voxel in the object, chunk by chunk.
+    pub fn solid_voxels(&self) -> impl Iterator<Item = (VoxelCoord, Voxel)> + '_ {
+        self.chunks().flat_map(|(chunk_coord, chunk)| {
+            ChunkIndex::iterate()
+                .filter(move |index| !chunk[*index].is_void())
+                .map(move |index| (VoxelCoord::from_chunk(chunk_coord, index), chunk[index]))
+        })
+    }
+
+    /// Box covering every allocated chunk, or `None` if there are none. Only as tight as the
+    /// chunk grid, but cheap.
+    pub fn chunk_bounds(&self) -> Option<VoxelBox> {
+        self.chunks()
+            .map(|(coord, _)| coord.voxel_box())
+            .reduce(|a, b| {
+                VoxelBox::new(
+                    VoxelCoord {
+                        vec: a.min.vec.min_by_component(b.min.vec),
+                    },
+                    VoxelCoord {
+                        vec: a.max.vec.max_by_component(b.max.vec),
+                    },
+                )
+            })
+    }
+
+    /// Copies out every voxel in `bounds`, in [`VoxelBox::iterate`] order.
+    pub fn read_box(&self, bounds: VoxelBox) -> Vec<Voxel> {
+        let mut voxels = vec![Voxel::VOID; bounds.volume()];
+        for chunk_coord in bounds.chunks() {
+            if let Some(chunk) = self.chunk(chunk_coord) {
+                let region = bounds.intersection(&chunk_coord.voxel_box()).unwrap();
+                for coord in region.iterate() {
+                    voxels[bounds.index_of(coord)] = chunk[coord.chunk_index()]
+                }
+            }
+        }
+        voxels
+    }
+
+    /// Rewrites every voxel in `bounds` with `f(coord, old)`, looking each chunk up once.
+    /// Chunks that don't exist yet are only created if `f` puts something solid in them.
+    pub fn map_box(&mut self, bounds: VoxelBox, mut f: impl FnMut(VoxelCoord, Voxel) -> Voxel) {
+        for chunk_coord in bounds.chunks() {
+            let region = bounds.intersection(&chunk_coord.voxel_box()).unwrap();
+            self.map_chunk(chunk_coord, region.iterate(), &mut f)
+        }
+    }
+
+    /// Like [`Object::map_box`], but over an arbitrary set of voxels, which are grouped by
+    /// chunk first.
+    pub fn map_voxels(
+        &mut self,
+        coords: impl IntoIterator<Item = VoxelCoord>,
+        mut f: impl FnMut(VoxelCoord, Voxel) -> Voxel,
+    ) {
+        let mut by_chunk = std::collections::HashMap::<_, Vec<_>>::new();
+        for coord in coords {
+            by_chunk.entry(coord.chunk()).or_default().push(coord)
+        }
+        for (chunk_coord, coords) in by_chunk {
+            self.map_chunk(chunk_coord, coords.into_iter(), &mut f)
+        }
+    }
+
+    /// Recolors the 6-connected patch of voxels sharing `start`'s material with `voxel`.
+    /// Gives up without changing anything if the patch is bigger than `limit` voxels, which
+    /// keeps fills in open space from running away. Returns how many voxels were filled.
+    pub fn flood_fill(&mut self, start: VoxelCoord, voxel: Voxel, limit: usize) -> Option<usize> {
+        let target = self[start];
+        if target == voxel {
+            return Some(0);
+        }
+        let mut visited = std::collections::HashSet::new();
+        let mut queue = std::collections::VecDeque::new();
+        visited.insert(start);
+        queue.push_back(start);
+        while let Some(coord) = queue.pop_front() {
+            for direction in Direction::iterate() {
+                let neighbor = coord.advance(direction);
+                if self[neighbor] == target && visited.insert(neighbor) {
+                    if visited.len() > limit {
+                        return None;
+                    }
+                    queue.push_back(neighbor)
+                }
+            }
+        }
+        let filled = visited.len();
+        self.map_voxels(visited, |_, _| voxel);
+        Some(filled)
+    }
+
+    /// Swaps every `from` voxel in `region` for `to`. Returns how many were replaced.
+    pub fn replace(&mut self, region: VoxelBox, from: Voxel, to: Voxel) -> usize {
+        let mut replaced = 0;
+        self.map_box(region, |_, old| {
+            if old == from {
+                replaced += 1;
+                to
+            } else {
+                old
+            }
+        });
+        replaced
+    }
+
+    fn map_chunk(
+        &mut self,
+        chunk_coord: ChunkCoord,
+        coords: impl Iterator<Item = VoxelCoord>,
+        f: &mut impl FnMut(VoxelCoord, Voxel) -> Voxel,
+    ) {
+        match self.chunks.get_mut(&chunk_coord) {
+            Some(chunk) => {
+                for coord in coords {
+                    let index = coord.chunk_index();
+                    chunk[index] = f(coord, chunk[index])
+                }
+            }
+            None => {
+                let mut new_chunk = None;
+                for coord in coords {
+                    let voxel = f(coord, Voxel::VOID);
+                    if !voxel.is_void() {
+                        new_chunk.get_or_insert_with(Chunk::new_void)[coord.chunk_index()] = voxel
+                    }
+                }
+                if let Some(chunk) = new_chunk {
+                    self.chunks.insert(chunk_coord, chunk);
+                }
+            }
+        }
     }
 }
 
@@ -0,0 +1,86 @@
+use super::{Direction, Object, VoxelCoord};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RaycastHit {
+    pub coord: VoxelCoord,
+    /// Face the ray entered the voxel through, or `None` if it started inside it.
+    pub face: Option<Direction>,
+    /// Distance along the ray to the hit, in voxels.
+    pub distance: f32,
+}
+
+impl RaycastHit {
+    /// Empty voxel in front of the face that was hit, where a new voxel would be placed.
+    pub fn adjacent(&self) -> VoxelCoord {
+        match self.face {
+            Some(face) => self.coord.advance(face),
+            None => self.coord,
+        }
+    }
+}
+
+impl Object {
+    /// First solid voxel along the ray within `max_distance`, stepping voxel by voxel
+    /// (Amanatides & Woo).
+    pub fn raycast(
+        &self,
+        origin: uv::Vec3,
+        direction: uv::Vec3,
+        max_distance: f32,
+    ) -> Option<RaycastHit> {
+        if direction.mag_sq() == 0.0 {
+            return None;
+        }
+        let direction = direction.normalized();
+        // Voxels are centered on their coordinates, so shift by half a voxel to make voxel
+        // `n` span `[n, n + 1)`.
+        let start = origin + uv::Vec3::broadcast(0.5);
+        let mut coord = VoxelCoord::containing(origin);
+
+        let mut step = uv::IVec3::zero();
+        let mut t_max = uv::Vec3::broadcast(f32::INFINITY);
+        let mut t_delta = uv::Vec3::broadcast(f32::INFINITY);
+        for axis in 0..3 {
+            if direction[axis] > 0.0 {
+                step[axis] = 1;
+                t_max[axis] = (coord.vec[axis] as f32 + 1.0 - start[axis]) / direction[axis];
+                t_delta[axis] = 1.0 / direction[axis]
+            } else if direction[axis] < 0.0 {
+                step[axis] = -1;
+                t_max[axis] = (start[axis] - coord.vec[axis] as f32) / -direction[axis];
+                t_delta[axis] = 1.0 / -direction[axis]
+            }
+        }
+
+        let mut distance = 0.0;
+        let mut face = None;
+        while distance <= max_distance {
+            if !self[coord].is_void() {
+                return Some(RaycastHit {
+                    coord,
+                    face,
+                    distance,
+                });
+            }
+            let axis = if t_max.x < t_max.y && t_max.x < t_max.z {
+                0
+            } else if t_max.y < t_max.z {
+                1
+            } else {
+                2
+            };
+            distance = t_max[axis];
+            coord.vec[axis] += step[axis];
+            t_max[axis] += t_delta[axis];
+            face = Some(match (axis, step[axis] > 0) {
+                (0, true) => Direction::NegX,
+                (0, false) => Direction::PosX,
+                (1, true) => Direction::NegY,
+                (1, false) => Direction::PosY,
+                (_, true) => Direction::NegZ,
+                (_, false) => Direction::PosZ,
+            })
+        }
+        None
+    }
+}
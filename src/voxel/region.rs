@@ -0,0 +1,99 @@
+use super::{Axis, Object, Voxel, VoxelBox, VoxelCoord};
+
+/// A box of voxels lifted out of an [`Object`], e.g. for copy and paste.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct VoxelRegion {
+    size: uv::IVec3,
+    voxels: Vec<Voxel>,
+}
+
+impl VoxelRegion {
+    pub fn copy(object: &Object, bounds: VoxelBox) -> Self {
+        Self {
+            size: bounds.size(),
+            voxels: object.read_box(bounds),
+        }
+    }
+
+    pub fn cut(object: &mut Object, bounds: VoxelBox) -> Self {
+        let region = Self::copy(object, bounds);
+        object.map_box(bounds, |_, _| Voxel::VOID);
+        region
+    }
+
+    pub fn size(&self) -> uv::IVec3 {
+        self.size
+    }
+
+    /// Box the region covers when pasted at `at`.
+    pub fn bounds_at(&self, at: VoxelCoord) -> VoxelBox {
+        VoxelBox {
+            min: at,
+            max: VoxelCoord {
+                vec: at.vec + self.size - uv::IVec3::one(),
+            },
+        }
+    }
+
+    /// Writes the region into `object` with its minimum corner at `at`. With `skip_void`,
+    /// void voxels in the region leave the object untouched instead of clearing it.
+    pub fn paste(&self, object: &mut Object, at: VoxelCoord, skip_void: bool) {
+        let bounds = self.bounds_at(at);
+        object.map_box(bounds, |coord, old| {
+            let voxel = self.voxels[bounds.index_of(coord)];
+            if skip_void && voxel.is_void() {
+                old
+            } else {
+                voxel
+            }
+        })
+    }
+
+    /// Rotates the region by `quarter_turns` counterclockwise quarter turns around `axis`.
+    pub fn rotated(&self, axis: Axis, quarter_turns: i32) -> Self {
+        (0..quarter_turns.rem_euclid(4)).fold(self.clone(), |region, _| region.quarter_turn(axis))
+    }
+
+    /// Mirrors the region along `axis`.
+    pub fn mirrored(&self, axis: Axis) -> Self {
+        let size = self.size;
+        self.remapped(size, |mut p| {
+            p[axis as usize] = size[axis as usize] - 1 - p[axis as usize];
+            p
+        })
+    }
+
+    fn quarter_turn(&self, axis: Axis) -> Self {
+        let s = self.size;
+        let size = match axis {
+            Axis::X => uv::IVec3::new(s.x, s.z, s.y),
+            Axis::Y => uv::IVec3::new(s.z, s.y, s.x),
+            Axis::Z => uv::IVec3::new(s.y, s.x, s.z),
+        };
+        self.remapped(size, |p| match axis {
+            Axis::X => uv::IVec3::new(p.x, size.y - 1 - p.z, p.y),
+            Axis::Y => uv::IVec3::new(p.z, p.y, size.z - 1 - p.x),
+            Axis::Z => uv::IVec3::new(size.x - 1 - p.y, p.x, p.z),
+        })
+    }
+
+    /// Rearranges the voxels into a region of `size`, moving each from local position `p`
+    /// to `map(p)`.
+    fn remapped(&self, size: uv::IVec3, map: impl Fn(uv::IVec3) -> uv::IVec3) -> Self {
+        let old_bounds = self.bounds_at(VoxelCoord::new(0, 0, 0));
+        let new_bounds = VoxelBox {
+            min: VoxelCoord::new(0, 0, 0),
+            max: VoxelCoord {
+                vec: size - uv::IVec3::one(),
+            },
+        };
+        let mut voxels = vec![Voxel::VOID; self.voxels.len()];
+        for coord in old_bounds.iterate() {
+            let new_coord = VoxelCoord {
+                vec: map(coord.vec),
+            };
+            voxels[new_bounds.index_of(new_coord)] = self.voxels[old_bounds.index_of(coord)]
+        }
+        Self { size, voxels }
+    }
+}
@@ -0,0 +1,120 @@
+use super::sdf::{self, Plane, Sdf};
+use super::{Direction, Object, Voxel, VoxelBox, VoxelCoord};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SculptTool {
+    /// Adds the brush shape itself.
+    Add,
+    /// Carves the brush shape out.
+    Carve,
+    /// Grows existing surfaces outwards, at most one voxel per stroke. Higher `strength`
+    /// also grows into spots with fewer solid neighbors.
+    Inflate { strength: f32 },
+    /// Fills dents and shaves off bumps.
+    Smooth,
+    /// Pulls the surface towards a plane, solid behind it.
+    Flatten(Plane),
+    /// Roughens the surface with value noise.
+    Noise {
+        amplitude: f32,
+        scale: f32,
+        seed: u32,
+    },
+}
+
+/// A sculpting stroke. Voxels are turned into a density field, edited with the brush
+/// `shape` weighting the tool, and quantised back to voxels.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sculpt<S> {
+    pub shape: S,
+    pub tool: SculptTool,
+    /// Material of voxels the stroke makes solid when none is nearby.
+    pub voxel: Voxel,
+    /// Width of the soft edge around `shape`, in voxels.
+    pub falloff: f32,
+    /// Density at or above which a voxel comes out solid.
+    pub threshold: f32,
+}
+
+impl<S: Sdf> Sculpt<S> {
+    pub fn new(shape: S, tool: SculptTool, voxel: Voxel) -> Self {
+        Self {
+            shape,
+            tool,
+            voxel,
+            falloff: 1.0,
+            threshold: 0.5,
+        }
+    }
+
+    /// Applies the stroke to the voxels of `object` in `region`.
+    pub fn apply(&self, object: &mut Object, region: VoxelBox) {
+        let outer = region.expanded(1);
+        let voxels = object.read_box(outer);
+        let voxel_at = |coord: VoxelCoord| voxels[outer.index_of(coord)];
+        let density_at = |coord: VoxelCoord| if voxel_at(coord).is_void() { 0.0 } else { 1.0 };
+        let blurred_at = |coord: VoxelCoord| {
+            let neighborhood = VoxelBox::new(coord, coord).expanded(1);
+            neighborhood.iterate().map(density_at).sum::<f32>() / 27.0
+        };
+
+        object.map_box(region, |coord, old| {
+            let pos = coord.pos();
+            let weight =
+                (0.5 - self.shape.distance(pos) / self.falloff.max(f32::EPSILON)).clamp(0.0, 1.0);
+            if weight == 0.0 {
+                return old;
+            }
+            let density = density_at(coord);
+            let density = match self.tool {
+                SculptTool::Add => density.max(weight),
+                SculptTool::Carve => density.min(1.0 - weight),
+                SculptTool::Inflate { strength } => {
+                    density.max(weight * (blurred_at(coord) + strength))
+                }
+                SculptTool::Smooth => lerp(density, blurred_at(coord), weight),
+                SculptTool::Flatten(plane) => {
+                    let target = (0.5 - plane.distance(pos)).clamp(0.0, 1.0);
+                    lerp(density, target, weight)
+                }
+                SculptTool::Noise {
+                    amplitude,
+                    scale,
+                    seed,
+                } => {
+                    let offset = amplitude * sdf::noise(pos / scale.max(f32::EPSILON), seed);
+                    lerp(density, blurred_at(coord) + offset, weight)
+                }
+            };
+            match (density >= self.threshold, old.is_void()) {
+                (true, true) if self.tool == SculptTool::Add => self.voxel,
+                (true, true) => self.material_near(coord, &voxel_at),
+                (true, false) => old,
+                (false, _) => Voxel::VOID,
+            }
+        })
+    }
+
+    /// Most common material among the face neighbors, or the brush's own.
+    fn material_near(&self, coord: VoxelCoord, voxel_at: &impl Fn(VoxelCoord) -> Voxel) -> Voxel {
+        let mut counts: Vec<(Voxel, u32)> = Vec::with_capacity(Direction::COUNT);
+        for direction in Direction::iterate() {
+            let neighbor = voxel_at(coord.advance(direction));
+            if neighbor.is_void() {
+                continue;
+            }
+            match counts.iter_mut().find(|(voxel, _)| *voxel == neighbor) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((neighbor, 1)),
+            }
+        }
+        counts
+            .into_iter()
+            .max_by_key(|(_, count)| *count)
+            .map_or(self.voxel, |(voxel, _)| voxel)
+    }
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
@@ -0,0 +1,230 @@
+//! Signed distance fields, negative inside. Distances are in voxels.
+
+pub trait Sdf {
+    fn distance(&self, pos: uv::Vec3) -> f32;
+
+    fn union<B: Sdf>(self, other: B) -> Union<Self, B>
+    where
+        Self: Sized,
+    {
+        Union(self, other)
+    }
+
+    fn subtract<B: Sdf>(self, other: B) -> Subtraction<Self, B>
+    where
+        Self: Sized,
+    {
+        Subtraction(self, other)
+    }
+
+    fn intersect<B: Sdf>(self, other: B) -> Intersection<Self, B>
+    where
+        Self: Sized,
+    {
+        Intersection(self, other)
+    }
+
+    /// Union that blends the surfaces together over roughly `k` voxels.
+    fn smooth_union<B: Sdf>(self, other: B, k: f32) -> SmoothUnion<Self, B>
+    where
+        Self: Sized,
+    {
+        SmoothUnion {
+            a: self,
+            b: other,
+            k,
+        }
+    }
+
+    /// Moves the surface by up to `amplitude` voxels of noise with features about `scale` voxels
+    /// apart. A `scale` that isn't positive leaves the surface as it is.
+    fn displace(self, amplitude: f32, scale: f32, seed: u32) -> Displace<Self>
+    where
+        Self: Sized,
+    {
+        Displace {
+            sdf: self,
+            amplitude,
+            scale,
+            seed,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Sphere {
+    pub center: uv::Vec3,
+    pub radius: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Cuboid {
+    pub center: uv::Vec3,
+    pub half_extents: uv::Vec3,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Capsule {
+    pub a: uv::Vec3,
+    pub b: uv::Vec3,
+    pub radius: f32,
+}
+
+/// Torus lying in the xy plane.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Torus {
+    pub center: uv::Vec3,
+    pub major_radius: f32,
+    pub minor_radius: f32,
+}
+
+/// Half-space behind a plane. `normal` must be normalized.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Plane {
+    pub normal: uv::Vec3,
+    pub offset: f32,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Union<A, B>(pub A, pub B);
+
+/// `A` with `B` cut out of it.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Subtraction<A, B>(pub A, pub B);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Intersection<A, B>(pub A, pub B);
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SmoothUnion<A, B> {
+    pub a: A,
+    pub b: B,
+    pub k: f32,
+}
+
+/// Surface of `sdf` roughened by value noise.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Displace<S> {
+    pub sdf: S,
+    pub amplitude: f32,
+    pub scale: f32,
+    pub seed: u32,
+}
+
+impl Sdf for Sphere {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        (pos - self.center).mag() - self.radius
+    }
+}
+
+impl Sdf for Cuboid {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        let q = (pos - self.center).map(f32::abs) - self.half_extents;
+        q.max_by_component(uv::Vec3::zero()).mag() + q.component_max().min(0.0)
+    }
+}
+
+impl Sdf for Capsule {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        let (pa, ba) = (pos - self.a, self.b - self.a);
+        let len_sq = ba.mag_sq();
+        let t = if len_sq == 0.0 {
+            0.0
+        } else {
+            (pa.dot(ba) / len_sq).clamp(0.0, 1.0)
+        };
+        (pa - ba * t).mag() - self.radius
+    }
+}
+
+impl Sdf for Torus {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        let p = pos - self.center;
+        let ring = uv::Vec2::new(p.x, p.y).mag() - self.major_radius;
+        uv::Vec2::new(ring, p.z).mag() - self.minor_radius
+    }
+}
+
+impl Sdf for Plane {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        pos.dot(self.normal) - self.offset
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for Union<A, B> {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        self.0.distance(pos).min(self.1.distance(pos))
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for Subtraction<A, B> {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        self.0.distance(pos).max(-self.1.distance(pos))
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for Intersection<A, B> {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        self.0.distance(pos).max(self.1.distance(pos))
+    }
+}
+
+impl<A: Sdf, B: Sdf> Sdf for SmoothUnion<A, B> {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        let (a, b) = (self.a.distance(pos), self.b.distance(pos));
+        if self.k <= 0.0 {
+            return a.min(b);
+        }
+        let h = (0.5 + 0.5 * (b - a) / self.k).clamp(0.0, 1.0);
+        b + (a - b) * h - self.k * h * (1.0 - h)
+    }
+}
+
+impl<S: Sdf> Sdf for Displace<S> {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        let distance = self.sdf.distance(pos);
+        if self.scale <= 0.0 {
+            return distance;
+        }
+        distance + self.amplitude * noise(pos / self.scale, self.seed)
+    }
+}
+
+impl<S: Sdf + ?Sized> Sdf for &S {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        (**self).distance(pos)
+    }
+}
+
+impl<S: Sdf + ?Sized> Sdf for Box<S> {
+    fn distance(&self, pos: uv::Vec3) -> f32 {
+        (**self).distance(pos)
+    }
+}
+
+/// Smooth 3D value noise in `[-1, 1]` with a lattice spacing of 1.
+pub fn noise(pos: uv::Vec3, seed: u32) -> f32 {
+    let base = pos.map(f32::floor);
+    let frac = pos - base;
+    let fade = frac.map(|t| t * t * (3.0 - 2.0 * t));
+    let (x, y, z) = (base.x as i32, base.y as i32, base.z as i32);
+
+    let corner = |dx: i32, dy: i32, dz: i32| lattice(x + dx, y + dy, z + dz, seed);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), fade.x);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), fade.x);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), fade.x);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), fade.x);
+    lerp(lerp(x00, x10, fade.y), lerp(x01, x11, fade.y), fade.z)
+}
+
+fn lattice(x: i32, y: i32, z: i32, seed: u32) -> f32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x8DA6_B343)
+        ^ (y as u32).wrapping_mul(0xD816_3841)
+        ^ (z as u32).wrapping_mul(0xCB1A_B31F);
+    h = (h ^ (h >> 16)).wrapping_mul(0x7FEB_352D);
+    h = (h ^ (h >> 15)).wrapping_mul(0x846C_A68B);
+    h ^= h >> 16;
+    h as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
@@ -0,0 +1,102 @@
+use super::{Chunk, ChunkIndex, Object, Voxel, VoxelBox, VoxelCoord};
+
+/// Summary of what's in an [`Object`], as printed by `stannox info`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectStats {
+    pub chunks: usize,
+    /// Chunks that are allocated but hold only void.
+    pub empty_chunks: usize,
+    /// Tightest box around the solid voxels, or `None` if there are none.
+    pub bounds: Option<VoxelBox>,
+    /// Number of voxels of each solid material, by id.
+    pub materials: std::collections::BTreeMap<u32, usize>,
+    /// Bytes taken up by chunk storage.
+    pub chunk_bytes: usize,
+    /// Faces the mesher produces for the whole object.
+    pub faces: usize,
+}
+
+impl ObjectStats {
+    pub fn solid_voxels(&self) -> usize {
+        self.materials.values().sum()
+    }
+}
+
+impl Object {
+    /// Counts everything in [`ObjectStats`]. Meshes every chunk to count faces, so it's slow
+    /// on big objects.
+    pub fn stats(&self) -> ObjectStats {
+        let mut materials = std::collections::BTreeMap::new();
+        let mut bounds = None::<VoxelBox>;
+        for (coord, voxel) in self.solid_voxels() {
+            *materials.entry(voxel.0).or_insert(0) += 1;
+            bounds = Some(match bounds {
+                Some(bounds) => VoxelBox::new(
+                    VoxelCoord {
+                        vec: bounds.min.vec.min_by_component(coord.vec),
+                    },
+                    VoxelCoord {
+                        vec: bounds.max.vec.max_by_component(coord.vec),
+                    },
+                ),
+                None => VoxelBox::new(coord, coord),
+            })
+        }
+
+        let mut empty_chunks = 0;
+        let mut faces = 0;
+        for (coord, chunk) in self.chunks() {
+            let mesh = super::mesh::mesh_with_chunk(self, chunk, coord);
+            if ChunkIndex::iterate().all(|index| chunk[index].is_void()) {
+                empty_chunks += 1
+            }
+            faces += mesh.faces.len()
+        }
+
+        ObjectStats {
+            chunks: self.chunk_count(),
+            empty_chunks,
+            bounds,
+            materials,
+            chunk_bytes: self.chunk_count()
+                * (std::mem::size_of::<Chunk>()
+                    + Chunk::VOLUME as usize * std::mem::size_of::<Voxel>()),
+            faces,
+        }
+    }
+}
+
+impl std::fmt::Display for ObjectStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "chunks: {} ({} empty)", self.chunks, self.empty_chunks)?;
+        match self.bounds {
+            Some(bounds) => {
+                let size = bounds.size();
+                writeln!(
+                    f,
+                    "bounds: ({}, {}, {}) to ({}, {}, {}), {}x{}x{}",
+                    bounds.min.vec.x,
+                    bounds.min.vec.y,
+                    bounds.min.vec.z,
+                    bounds.max.vec.x,
+                    bounds.max.vec.y,
+                    bounds.max.vec.z,
+                    size.x,
+                    size.y,
+                    size.z
+                )?
+            }
+            None => writeln!(f, "bounds: none")?,
+        }
+        writeln!(f, "solid voxels: {}", self.solid_voxels())?;
+        for (id, count) in &self.materials {
+            writeln!(f, "  material {}: {}", id, count)?
+        }
+        writeln!(
+            f,
+            "chunk memory: {:.1} MiB",
+            self.chunk_bytes as f64 / (1024.0 * 1024.0)
+        )?;
+        writeln!(f, "mesh faces: {}", self.faces)
+    }
+}